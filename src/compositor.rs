@@ -1,168 +1,395 @@
-use std::{collections::HashMap, ops::Deref};
+use std::{collections::{HashMap, VecDeque}, ops::Deref, sync::{Arc, Mutex, mpsc}, time::{Duration, Instant}};
 use async_channel::{Receiver, Sender};
-use niri_ipc::{Action, Event, Output, Reply, Request, Workspace, socket::Socket};
-use crate::{errors::ModuleError, settings::Settings};
+use niri_ipc::{Action, Event, Output, Reply, Request, Workspace, WorkspaceReferenceArg, socket::Socket};
+use crate::{errors::ModuleError, settings::{FloatingPosition, OrderMode, OrderWeights, OverviewBehavior, RepositionMode, Settings}};
 
 #[derive(Debug, Clone)]
 pub struct CompositorClient {
     settings: Settings,
+    command_socket: Arc<CommandSocket>,
+}
+
+/// Connection reused for requests and actions; event streams keep their own.
+#[derive(Default)]
+struct CommandSocket(Mutex<Option<Socket>>);
+
+impl std::fmt::Debug for CommandSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CommandSocket")
+    }
 }
 
 impl CompositorClient {
     pub fn create(settings: Settings) -> Self {
-        Self { settings }
+        Self { settings, command_socket: Arc::default() }
+    }
+
+    /// Sends `request` over the shared command socket, connecting on first
+    /// use. If a reused connection fails (e.g. niri restarted), the request
+    /// is sent once more on a fresh one.
+    #[tracing::instrument(level = "TRACE", skip(self), err)]
+    fn send_request(&self, request: Request) -> Result<Reply, ModuleError> {
+        let mut socket = self.command_socket.0.lock().expect("command socket lock");
+
+        if let Some(mut reused) = socket.take() {
+            match reused.send(request.clone()) {
+                Ok(reply) => {
+                    *socket = Some(reused);
+                    return Ok(reply);
+                }
+                Err(e) => tracing::debug!(%e, "command socket went stale, reconnecting"),
+            }
+        }
+
+        let mut fresh = connect_socket()?;
+        let reply = fresh.send(request).map_err(ModuleError::CompositorIpc)?;
+        *socket = Some(fresh);
+        Ok(reply)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn focus_window(&self, window_id: u64) -> Result<(), ModuleError> {
-        let response = send_request(Request::Action(Action::FocusWindow { id: window_id }))?;
+        let response = self.send_request(Request::Action(Action::FocusWindow { id: window_id }))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn close_window(&self, window_id: u64) -> Result<(), ModuleError> {
-        let response = send_request(Request::Action(Action::CloseWindow { id: Some(window_id) }))?;
+        let response = self.send_request(Request::Action(Action::CloseWindow { id: Some(window_id) }))?;
         validate_handled(response)
     }
 
+    /// Closes every window with the given app ID and returns how many were
+    /// closed. Windows that disappear in the meantime (e.g. dialogs closed
+    /// together with their parent) are skipped.
+    #[tracing::instrument(level = "TRACE", err)]
+    pub fn close_app_windows(&self, app_id: &str) -> Result<usize, ModuleError> {
+        let window_ids: Vec<u64> = self.query_windows()?
+            .into_iter()
+            .filter(|w| w.app_id.as_deref() == Some(app_id))
+            .map(|w| w.id)
+            .collect();
+
+        self.close_windows(&window_ids)
+    }
+
+    /// Ids of the windows sharing `window_id`'s workspace, including itself.
+    /// Workspace ids are unique across outputs, unlike their indices, so this
+    /// never reaches windows on another output.
+    pub fn workspace_window_ids(&self, window_id: u64) -> Result<Vec<u64>, ModuleError> {
+        let windows = self.query_windows()?;
+        let Some(workspace_id) = windows.iter().find(|w| w.id == window_id).and_then(|w| w.workspace_id) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(windows.into_iter()
+            .filter(|w| w.workspace_id == Some(workspace_id))
+            .map(|w| w.id)
+            .collect())
+    }
+
+    /// Closes each window, skipping ones that are already gone, and returns
+    /// how many were closed.
+    pub fn close_windows(&self, window_ids: &[u64]) -> Result<usize, ModuleError> {
+        let mut closed = 0;
+        for &window_id in window_ids {
+            match self.close_window(window_id) {
+                Ok(()) => closed += 1,
+                Err(ModuleError::CompositorReply(msg)) => {
+                    tracing::debug!(window_id, msg, "window already gone");
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(closed)
+    }
+
     #[tracing::instrument(level = "TRACE", err)]
     pub fn maximize_window_column(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::MaximizeColumn {}))?;
+        let response = self.send_request(Request::Action(Action::MaximizeColumn {}))?;
         validate_handled(response)
     }
 
 	#[tracing::instrument(level = "TRACE", err)]
 	pub fn maximize_window_to_edges(&self, window_id: u64) -> Result<(), ModuleError> {
 		self.focus_window(window_id)?;
-		let response = send_request(Request::Action(Action::MaximizeWindowToEdges { id: Some(window_id) }))?;
+		let response = self.send_request(Request::Action(Action::MaximizeWindowToEdges { id: Some(window_id) }))?;
 		validate_handled(response)
 	}
 
 	#[tracing::instrument(level = "TRACE", err)]
 	pub fn center_column(&self, window_id: u64) -> Result<(), ModuleError> {
 		self.focus_window(window_id)?;
-		let response = send_request(Request::Action(Action::CenterColumn {}))?;
+		let response = self.send_request(Request::Action(Action::CenterColumn {}))?;
 		validate_handled(response)
 	}
 
 	#[tracing::instrument(level = "TRACE", err)]
 	pub fn fullscreen_window(&self, window_id: u64) -> Result<(), ModuleError> {
-		let response = send_request(Request::Action(Action::FullscreenWindow { id: Some(window_id) }))?;
+		let response = self.send_request(Request::Action(Action::FullscreenWindow { id: Some(window_id) }))?;
 		validate_handled(response)
 	}
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn toggle_floating(&self, window_id: u64) -> Result<(), ModuleError> {
-        let response = send_request(Request::Action(Action::ToggleWindowFloating { id: Some(window_id) }))?;
+        let response = self.send_request(Request::Action(Action::ToggleWindowFloating { id: Some(window_id) }))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn center_window(&self, window_id: u64) -> Result<(), ModuleError> {
-        let response = send_request(Request::Action(Action::CenterWindow { id: Some(window_id) }))?;
+        let response = self.send_request(Request::Action(Action::CenterWindow { id: Some(window_id) }))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn center_visible_columns(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::CenterVisibleColumns {}))?;
+        let response = self.send_request(Request::Action(Action::CenterVisibleColumns {}))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn expand_column_to_available_width(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::ExpandColumnToAvailableWidth {}))?;
+        let response = self.send_request(Request::Action(Action::ExpandColumnToAvailableWidth {}))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn toggle_windowed_fullscreen(&self, window_id: u64) -> Result<(), ModuleError> {
-        let response = send_request(Request::Action(Action::ToggleWindowedFullscreen { id: Some(window_id) }))?;
+        let response = self.send_request(Request::Action(Action::ToggleWindowedFullscreen { id: Some(window_id) }))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn consume_window_into_column(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::ConsumeWindowIntoColumn {}))?;
+        let response = self.send_request(Request::Action(Action::ConsumeWindowIntoColumn {}))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn expel_window_from_column(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::ExpelWindowFromColumn {}))?;
+        let response = self.send_request(Request::Action(Action::ExpelWindowFromColumn {}))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn reset_window_height(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::ResetWindowHeight { id: None }))?;
+        let response = self.send_request(Request::Action(Action::ResetWindowHeight { id: None }))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn switch_preset_column_width(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::SwitchPresetColumnWidth {}))?;
+        let response = self.send_request(Request::Action(Action::SwitchPresetColumnWidth {}))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn switch_preset_window_height(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::SwitchPresetWindowHeight { id: None }))?;
+        let response = self.send_request(Request::Action(Action::SwitchPresetWindowHeight { id: None }))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn move_window_to_workspace_down(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::MoveWindowToWorkspaceDown { focus: false }))?;
+        let response = self.send_request(Request::Action(Action::MoveWindowToWorkspaceDown { focus: false }))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn move_window_to_workspace_up(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::MoveWindowToWorkspaceUp { focus: false }))?;
+        let response = self.send_request(Request::Action(Action::MoveWindowToWorkspaceUp { focus: false }))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn move_window_to_monitor_left(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::MoveWindowToMonitorLeft {}))?;
+        let response = self.send_request(Request::Action(Action::MoveWindowToMonitorLeft {}))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn move_window_to_monitor_right(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::MoveWindowToMonitorRight {}))?;
+        let response = self.send_request(Request::Action(Action::MoveWindowToMonitorRight {}))?;
+        validate_handled(response)
+    }
+
+    /// Unlike the directional moves, this names the output, which is checked
+    /// first so a typo is reported instead of silently doing nothing.
+    #[tracing::instrument(level = "TRACE", err)]
+    pub fn move_window_to_named_monitor(&self, window_id: u64, output: &str) -> Result<(), ModuleError> {
+        if !self.query_outputs()?.contains_key(output) {
+            return Err(ModuleError::UnknownOutput(output.to_string()));
+        }
+        let response = self.send_request(Request::Action(Action::MoveWindowToMonitor {
+            id: Some(window_id),
+            output: output.to_string(),
+        }))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn toggle_column_tabbed_display(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::ToggleColumnTabbedDisplay {}))?;
+        let response = self.send_request(Request::Action(Action::ToggleColumnTabbedDisplay {}))?;
         validate_handled(response)
     }
 
     #[tracing::instrument(level = "TRACE", err)]
     pub fn focus_workspace_previous(&self, window_id: u64) -> Result<(), ModuleError> {
         self.focus_window(window_id)?;
-        let response = send_request(Request::Action(Action::FocusWorkspacePrevious {}))?;
+        let response = self.send_request(Request::Action(Action::FocusWorkspacePrevious {}))?;
+        validate_handled(response)
+    }
+
+    /// Focuses the workspace above (`up`) or below the focused one.
+    #[tracing::instrument(level = "TRACE", err)]
+    pub fn focus_workspace_relative(&self, up: bool) -> Result<(), ModuleError> {
+        let action = if up { Action::FocusWorkspaceUp {} } else { Action::FocusWorkspaceDown {} };
+        let response = self.send_request(Request::Action(action))?;
         validate_handled(response)
     }
 
+    /// Moves the window to the named minimize workspace and returns the id of
+    /// the workspace it came from, so it can be restored later. A window that
+    /// is already minimized stays put and reports no origin, so the one
+    /// remembered from the first minimize isn't replaced.
+    #[tracing::instrument(level = "TRACE", err)]
+    pub fn minimize_window(&self, window_id: u64) -> Result<Option<u64>, ModuleError> {
+        let origin = self.query_windows()?
+            .into_iter()
+            .find(|w| w.id == window_id)
+            .and_then(|w| w.workspace_id);
+
+        let minimize_workspace = self.settings.minimize_workspace();
+        let already_minimized = match origin {
+            Some(workspace_id) => self.query_workspaces()?
+                .iter()
+                .any(|ws| ws.id == workspace_id && ws.name.as_deref() == Some(minimize_workspace)),
+            None => false,
+        };
+        if already_minimized {
+            tracing::debug!(id = window_id, "window is already minimized");
+            return Ok(None);
+        }
+
+        let response = self.send_request(Request::Action(Action::MoveWindowToWorkspace {
+            window_id: Some(window_id),
+            reference: WorkspaceReferenceArg::Name(self.settings.minimize_workspace().to_string()),
+            focus: false,
+        }))?;
+        validate_handled(response)?;
+
+        Ok(origin)
+    }
+
+    /// Whether the window currently sits on the minimize workspace.
+    #[tracing::instrument(level = "TRACE", err)]
+    pub fn is_window_minimized(&self, window_id: u64) -> Result<bool, ModuleError> {
+        let Some(workspace_id) = self.query_windows()?
+            .into_iter()
+            .find(|w| w.id == window_id)
+            .and_then(|w| w.workspace_id)
+        else {
+            return Ok(false);
+        };
+
+        Ok(self.query_workspaces()?
+            .into_iter()
+            .any(|ws| ws.id == workspace_id && ws.name.as_deref() == Some(self.settings.minimize_workspace())))
+    }
+
+    #[tracing::instrument(level = "TRACE", err)]
+    pub fn is_window_focused(&self, window_id: u64) -> Result<bool, ModuleError> {
+        Ok(self.query_windows()?
+            .into_iter()
+            .any(|w| w.id == window_id && w.is_focused))
+    }
+
+    /// Moves the window back to `origin`, or to the focused workspace when the
+    /// origin is unknown or has since been removed, and focuses it.
+    #[tracing::instrument(level = "TRACE", err)]
+    pub fn restore_window(&self, window_id: u64, origin: Option<u64>) -> Result<(), ModuleError> {
+        let origin = match origin {
+            Some(workspace_id) if !self.query_workspaces()?.iter().any(|ws| ws.id == workspace_id) => {
+                tracing::debug!(id = window_id, workspace_id, "origin workspace is gone");
+                None
+            }
+            origin => origin,
+        };
+        match origin {
+            Some(workspace_id) => self.move_window_and_focus(window_id, workspace_id),
+            None => self.summon_window(window_id),
+        }
+    }
+
+    /// Pulls the window onto the focused workspace and focuses it.
+    #[tracing::instrument(level = "TRACE", err)]
+    pub fn summon_window(&self, window_id: u64) -> Result<(), ModuleError> {
+        let focused_workspace = self.query_workspaces()?
+            .into_iter()
+            .find(|ws| ws.is_focused)
+            .map(|ws| ws.id);
+
+        match focused_workspace {
+            Some(workspace_id) => self.move_window_and_focus(window_id, workspace_id),
+            None => {
+                tracing::warn!(id = window_id, "no focused workspace to move window to");
+                self.focus_window(window_id)
+            }
+        }
+    }
+
+    fn move_window_and_focus(&self, window_id: u64, workspace_id: u64) -> Result<(), ModuleError> {
+        let response = self.send_request(Request::Action(Action::MoveWindowToWorkspace {
+            window_id: Some(window_id),
+            reference: WorkspaceReferenceArg::Id(workspace_id),
+            focus: true,
+        }))?;
+        validate_handled(response)?;
+        self.focus_window(window_id)
+    }
+
+    fn query_windows(&self) -> Result<Vec<niri_ipc::Window>, ModuleError> {
+        match self.send_request(Request::Windows)? {
+            Ok(niri_ipc::Response::Windows(windows)) => Ok(windows),
+            Ok(other) => Err(ModuleError::unexpected_response("Windows", other)),
+            Err(msg) => Err(ModuleError::CompositorReply(msg)),
+        }
+    }
+
+    #[tracing::instrument(level = "TRACE", err)]
+    pub fn focused_output_name(&self) -> Result<Option<String>, ModuleError> {
+        match self.send_request(Request::FocusedOutput)? {
+            Ok(niri_ipc::Response::FocusedOutput(output)) => Ok(output.map(|o| o.name)),
+            Ok(other) => Err(ModuleError::unexpected_response("FocusedOutput", other)),
+            Err(msg) => Err(ModuleError::CompositorReply(msg)),
+        }
+    }
+
+    fn query_workspaces(&self) -> Result<Vec<Workspace>, ModuleError> {
+        match self.send_request(Request::Workspaces)? {
+            Ok(niri_ipc::Response::Workspaces(workspaces)) => Ok(workspaces),
+            Ok(other) => Err(ModuleError::unexpected_response("Workspaces", other)),
+            Err(msg) => Err(ModuleError::CompositorReply(msg)),
+        }
+    }
+
     pub fn query_outputs(&self) -> Result<HashMap<String, Output>, ModuleError> {
-        let response = send_request(Request::Outputs)?;
+        let response = self.send_request(Request::Outputs)?;
         match response {
             Ok(niri_ipc::Response::Outputs(outputs)) => Ok(outputs),
             Ok(other) => Err(ModuleError::unexpected_response("Outputs", other)),
@@ -171,7 +398,7 @@ impl CompositorClient {
     }
 
     pub fn create_window_stream(&self) -> WindowEventStream {
-        WindowEventStream::start(self.settings.only_current_workspace())
+        WindowEventStream::start(SnapshotOptions::from_settings(&self.settings))
     }
 
     pub fn create_workspace_stream(&self) -> WorkspaceEventStream {
@@ -186,12 +413,7 @@ impl CompositorClient {
 
         tracing::info!("repositioning window {} by {} columns", window_id, position_delta);
 
-        let response = send_request(Request::Windows)?;
-        let all_windows: Vec<niri_ipc::Window> = match response {
-            Ok(niri_ipc::Response::Windows(windows)) => windows,
-            Ok(other) => return Err(ModuleError::unexpected_response("Windows", other)),
-            Err(msg) => return Err(ModuleError::CompositorReply(msg)),
-        };
+        let all_windows = self.query_windows()?;
 
         let currently_focused = all_windows.iter().find(|w| w.is_focused).map(|w| w.id);
 
@@ -201,42 +423,76 @@ impl CompositorClient {
             return Ok(());
         };
 
-        let tile_position = target.layout.pos_in_scrolling_layout.map(|(_, tile)| tile).unwrap_or(1);
-        let is_stacked = tile_position > 1;
+        let Some((column, tile_position)) = target.layout.pos_in_scrolling_layout else {
+            tracing::debug!(window_id, "floating windows have no column to reposition");
+            return Ok(());
+        };
 
         self.focus_window(window_id)?;
 
+        match self.settings.reposition_mode() {
+            RepositionMode::Move => self.move_focused_column(column, tile_position > 1, position_delta)?,
+            RepositionMode::Swap => self.swap_focused_window(position_delta)?,
+        }
+
+        if let Some(original_focus) = currently_focused {
+            if original_focus != window_id {
+                self.focus_window(original_focus)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves the focused window's column, expelling the window into its own
+    /// column first if it is stacked. Jumps straight to the target index when
+    /// niri supports it and steps one column at a time otherwise.
+    fn move_focused_column(&self, column: usize, is_stacked: bool, position_delta: i32) -> Result<(), ModuleError> {
         if is_stacked {
             tracing::trace!("expelling stacked window from column");
-            let response = send_request(Request::Action(Action::ExpelWindowFromColumn {}))?;
+            let response = self.send_request(Request::Action(Action::ExpelWindowFromColumn {}))?;
             validate_handled(response)?;
         }
 
-        let (action, count) = if position_delta < 0 {
-            (Action::MoveColumnLeft {}, position_delta.abs())
+        let index = target_column_index(column, is_stacked, position_delta);
+        match self.send_request(Request::Action(Action::MoveColumnToIndex { index }))? {
+            Ok(niri_ipc::Response::Handled) => return Ok(()),
+            Ok(other) => return Err(ModuleError::unexpected_response("Handled", other)),
+            Err(msg) => tracing::debug!(%msg, index, "move-column-to-index unavailable, stepping instead"),
+        }
+
+        let action = if position_delta < 0 {
+            Action::MoveColumnLeft {}
         } else {
-            (Action::MoveColumnRight {}, position_delta)
+            Action::MoveColumnRight {}
         };
 
-        for _ in 0..count {
-            let response = send_request(Request::Action(action.clone()))?;
+        for _ in 0..position_delta.abs() {
+            let response = self.send_request(Request::Action(action.clone()))?;
             validate_handled(response)?;
         }
 
-        if let Some(original_focus) = currently_focused {
-            if original_focus != window_id {
-                self.focus_window(original_focus)?;
-            }
+        Ok(())
+    }
+
+    /// Swaps the focused window with its neighbouring column one step at a
+    /// time, leaving the rest of its column intact.
+    fn swap_focused_window(&self, position_delta: i32) -> Result<(), ModuleError> {
+        let action = if position_delta < 0 {
+            Action::SwapWindowLeft {}
+        } else {
+            Action::SwapWindowRight {}
+        };
+
+        for _ in 0..position_delta.abs() {
+            let response = self.send_request(Request::Action(action.clone()))?;
+            validate_handled(response)?;
         }
 
         Ok(())
     }
 }
 
-#[tracing::instrument(level = "TRACE", err)]
-fn send_request(request: Request) -> Result<Reply, ModuleError> {
-    connect_socket()?.send(request).map_err(ModuleError::CompositorIpc)
-}
 
 #[tracing::instrument(level = "TRACE", err)]
 fn connect_socket() -> Result<Socket, ModuleError> {
@@ -251,17 +507,30 @@ fn validate_handled(response: Reply) -> Result<(), ModuleError> {
     }
 }
 
+/// 1-based column index a column at `column` ends up at after moving by
+/// `position_delta`. Expelling a stacked window puts it in a new column right
+/// of its old one, so the move starts from there.
+fn target_column_index(column: usize, is_stacked: bool, position_delta: i32) -> usize {
+    let start = if is_stacked { column + 1 } else { column };
+    (start as i64 + position_delta as i64).max(1) as usize
+}
+
+/// Events the compositor sends that this niri-ipc version can't parse (e.g. a
+/// newer niri adding event variants) surface as `InvalidData`; socket failures
+/// and EOF use other kinds and stay fatal.
+fn is_unknown_event(error: &std::io::Error) -> bool {
+    error.kind() == std::io::ErrorKind::InvalidData
+}
+
 pub struct WindowEventStream {
     receiver: Receiver<WindowSnapshot>,
 }
 
 impl WindowEventStream {
-    fn start(filter_workspace: bool) -> Self {
+    fn start(options: SnapshotOptions) -> Self {
         let (tx, rx) = async_channel::unbounded();
         std::thread::spawn(move || {
-            if let Err(e) = run_window_stream(tx, filter_workspace) {
-                tracing::error!(%e, "window event stream terminated");
-            }
+            run_with_reconnect("window", || run_window_stream(tx.clone(), &options));
         });
 
         Self { receiver: rx }
@@ -280,9 +549,7 @@ impl WorkspaceEventStream {
     fn start() -> Self {
         let (tx, rx) = async_channel::unbounded();
         std::thread::spawn(move || {
-            if let Err(e) = run_workspace_stream(tx) {
-                tracing::error!(%e, "workspace event stream terminated");
-            }
+            run_with_reconnect("workspace", || run_workspace_stream(tx.clone()));
         });
 
         Self { receiver: rx }
@@ -293,6 +560,36 @@ impl WorkspaceEventStream {
     }
 }
 
+/// Re-runs an event stream whenever it fails (e.g. niri restarting), backing
+/// off exponentially between attempts. Each run opens a fresh socket and starts
+/// from an empty state, so the compositor's initial events rebuild it. Stops
+/// once the receiving side has gone away.
+fn run_with_reconnect(stream_name: &'static str, mut run: impl FnMut() -> Result<(), ModuleError>) {
+    const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+    const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        let started = Instant::now();
+        match run() {
+            Err(ModuleError::SnapshotChannelClosed) => {
+                tracing::info!(stream_name, "event stream receiver closed, stopping");
+                return;
+            }
+            Err(e) => tracing::error!(%e, stream_name, "event stream terminated"),
+            Ok(()) => tracing::warn!(stream_name, "event stream ended"),
+        }
+
+        if started.elapsed() > MAX_BACKOFF {
+            backoff = INITIAL_BACKOFF;
+        }
+
+        tracing::info!(stream_name, ?backoff, "reconnecting to compositor event stream");
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
 fn run_workspace_stream(tx: Sender<Vec<Workspace>>) -> Result<(), ModuleError> {
     let mut socket = connect_socket()?;
     let response = socket.send(Request::EventStream).map_err(ModuleError::CompositorIpc)?;
@@ -305,6 +602,9 @@ fn run_workspace_stream(tx: Sender<Vec<Workspace>>) -> Result<(), ModuleError> {
                 tx.send_blocking(workspaces).map_err(|_| ModuleError::SnapshotChannelClosed)?;
             }
             Ok(_) => {}
+            Err(e) if is_unknown_event(&e) => {
+                tracing::debug!(%e, "skipping unrecognized compositor event");
+            }
             Err(e) => {
                 tracing::error!(%e, "workspace event stream error");
                 return Err(ModuleError::CompositorIpc(e));
@@ -313,20 +613,43 @@ fn run_workspace_stream(tx: Sender<Vec<Workspace>>) -> Result<(), ModuleError> {
     }
 }
 
-fn run_window_stream(tx: Sender<WindowSnapshot>, filter_workspace: bool) -> Result<(), ModuleError> {
+fn run_window_stream(tx: Sender<WindowSnapshot>, options: &SnapshotOptions) -> Result<(), ModuleError> {
     let mut socket = connect_socket()?;
     let response = socket.send(Request::EventStream).map_err(ModuleError::CompositorIpc)?;
     validate_handled(response)?;
 
     let mut event_reader = socket.read_events();
-    let mut window_state = WindowTracker::new();
+    let window_state = Arc::new(Mutex::new(WindowTracker::new()));
+    // Dropped when this run ends, which stops the wakeup thread and discards
+    // wakeups meant for the old connection's windows.
+    let wakeups = options.min_window_age.map(|min_age| {
+        let (deadlines_tx, deadlines_rx) = mpsc::channel();
+        let window_state = window_state.clone();
+        let tx = tx.clone();
+        let options = options.clone();
+        std::thread::spawn(move || run_age_wakeups(deadlines_rx, window_state, tx, options));
+        (deadlines_tx, min_age)
+    });
 
     loop {
         match event_reader() {
             Ok(event) => {
-                if let Some(snapshot) = window_state.process_event(event, filter_workspace) {
+                let mut tracker = window_state.lock().expect("window tracker lock");
+                let opened = match &event {
+                    Event::WindowOpenedOrChanged { window } => tracker.is_new_window(window.id),
+                    _ => false,
+                };
+                if let Some(snapshot) = tracker.process_event(event, options) {
                     tx.send_blocking(snapshot).map_err(|_| ModuleError::SnapshotChannelClosed)?;
                 }
+                drop(tracker);
+
+                if let Some((deadlines, min_age)) = wakeups.as_ref().filter(|_| opened) {
+                    let _ = deadlines.send(Instant::now() + *min_age);
+                }
+            }
+            Err(e) if is_unknown_event(&e) => {
+                tracing::debug!(%e, "skipping unrecognized compositor event");
             }
             Err(e) => {
                 tracing::error!(%e, "event stream read error");
@@ -336,9 +659,76 @@ fn run_window_stream(tx: Sender<WindowSnapshot>, filter_workspace: bool) -> Resu
     }
 }
 
+/// Snapshots only follow events, so this sends one whenever a new window
+/// becomes old enough to show, in case nothing else happens. Deadlines arrive
+/// in order since they all add the same `min_window_age`. Returns once the
+/// sending side is dropped.
+fn run_age_wakeups(
+    deadlines: mpsc::Receiver<Instant>,
+    window_state: Arc<Mutex<WindowTracker>>,
+    tx: Sender<WindowSnapshot>,
+    options: SnapshotOptions,
+) {
+    let mut pending = VecDeque::new();
+    loop {
+        let received = match pending.front() {
+            Some(&deadline) => deadlines.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => deadlines.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            Ok(deadline) => pending.push_back(deadline),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let now = Instant::now();
+                while pending.front().is_some_and(|&deadline| deadline <= now) {
+                    pending.pop_front();
+                }
+                let snapshot = window_state.lock().expect("window tracker lock").snapshot(&options);
+                if let Some(snapshot) = snapshot {
+                    if tx.send_blocking(snapshot).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// Settings that shape the snapshots produced by the window stream thread.
+#[derive(Debug, Clone)]
+struct SnapshotOptions {
+    filter_workspace: bool,
+    visible_workspaces: Option<Vec<String>>,
+    floating_position: FloatingPosition,
+    order_mode: OrderMode,
+    overview_behavior: OverviewBehavior,
+    min_window_age: Option<Duration>,
+    order_weights: Option<OrderWeights>,
+}
+
+impl SnapshotOptions {
+    fn from_settings(settings: &Settings) -> Self {
+        Self {
+            filter_workspace: settings.only_current_workspace(),
+            visible_workspaces: settings.visible_workspaces().map(<[String]>::to_vec),
+            floating_position: settings.floating_position(),
+            order_mode: settings.order_mode(),
+            overview_behavior: settings.overview_behavior(),
+            min_window_age: settings.min_window_age_ms().map(Duration::from_millis),
+            order_weights: settings.order_weights(),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct WindowTracker {
     state: Option<TrackerState>,
+    /// Window ids, most recently focused first.
+    focus_order: Vec<u64>,
+    overview_open: bool,
+    /// When windows opened after the initial window list arrived.
+    opened_at: HashMap<u64, Instant>,
 }
 
 #[derive(Debug)]
@@ -355,15 +745,31 @@ enum TrackerState {
 
 impl WindowTracker {
     fn new() -> Self {
-        Self { state: None }
+        Self { state: None, focus_order: Vec::new(), overview_open: false, opened_at: HashMap::new() }
+    }
+
+    /// Whether `window_id` would be a window opening, rather than one the
+    /// tracker already knows or one from the initial window list.
+    fn is_new_window(&self, window_id: u64) -> bool {
+        matches!(&self.state, Some(TrackerState::Ready { windows, .. }) if !windows.contains_key(&window_id))
+    }
+
+    fn record_focus(&mut self, window_id: u64) {
+        self.focus_order.retain(|&id| id != window_id);
+        self.focus_order.insert(0, window_id);
     }
 
 	#[tracing::instrument(level = "TRACE", skip(self))]
-    fn process_event(&mut self, event: Event, filter_workspace: bool) -> Option<WindowSnapshot> {
+    fn process_event(&mut self, event: Event, options: &SnapshotOptions) -> Option<WindowSnapshot> {
         use TrackerState::*;
 
         match event {
             Event::WindowsChanged { windows } => {
+                self.focus_order.retain(|id| windows.iter().any(|w| w.id == *id));
+                self.opened_at.retain(|id, _| windows.iter().any(|w| w.id == *id));
+                if let Some(focused) = windows.iter().find(|w| w.is_focused) {
+                    self.record_focus(focused.id);
+                }
                 self.state = match self.state.take() {
                     Some(WorkspacesOnly(ws)) => Some(Ready {
                         windows: windows.iter().map(|w| (w.id, w.clone())).collect(),
@@ -398,11 +804,19 @@ impl WindowTracker {
                 };
             }
             Event::WindowClosed { id } => {
+                self.focus_order.retain(|&known| known != id);
+                self.opened_at.remove(&id);
                 if let Some(Ready { windows, .. }) = &mut self.state {
                     windows.remove(&id);
                 }
             }
             Event::WindowOpenedOrChanged { window } => {
+                if window.is_focused {
+                    self.record_focus(window.id);
+                }
+                if self.is_new_window(window.id) {
+                    self.opened_at.insert(window.id, Instant::now());
+                }
                 if let Some(Ready { windows, last_focused_per_workspace, .. }) = &mut self.state {
                     if window.is_focused {
                         if let Some(old_focused) = windows.values().find(|w| w.is_focused).map(|w| w.id) {
@@ -423,6 +837,9 @@ impl WindowTracker {
                 }
             }
             Event::WindowFocusChanged { id } => {
+                if let Some(focused_id) = id {
+                    self.record_focus(focused_id);
+                }
                 if let Some(Ready { windows, last_focused_per_workspace, .. }) = &mut self.state {
                     if let Some(old_focused) = windows.values().find(|w| w.is_focused).map(|w| w.id) {
                         if let Some(window) = windows.get(&old_focused) {
@@ -471,6 +888,13 @@ impl WindowTracker {
                     tracing::info!("active window map: {:?}", active_per_workspace);
                 }
             }
+            Event::WindowUrgencyChanged { id, urgent } => {
+                if let Some(Ready { windows, .. }) = &mut self.state {
+                    if let Some(window) = windows.get_mut(&id) {
+                        window.is_urgent = urgent;
+                    }
+                }
+            }
             Event::WindowLayoutsChanged { changes } => {
                 if let Some(Ready { windows, .. }) = &mut self.state {
                     for (win_id, layout) in changes {
@@ -482,11 +906,24 @@ impl WindowTracker {
                     }
                 }
             }
+            Event::OverviewOpenedOrClosed { is_open } => {
+                self.overview_open = is_open;
+            }
             _ => {}
         }
 
+        self.snapshot(options)
+    }
+
+    fn snapshot(&self, options: &SnapshotOptions) -> Option<WindowSnapshot> {
+        use TrackerState::*;
+
+        if self.overview_open && options.overview_behavior == OverviewBehavior::Freeze {
+            return None;
+        }
+
         if let Some(Ready { windows, workspaces, active_per_workspace, last_focused_per_workspace }) = &self.state {
-            Some(self.generate_snapshot(windows, workspaces, active_per_workspace, last_focused_per_workspace, filter_workspace))
+            Some(self.generate_snapshot(windows, workspaces, active_per_workspace, last_focused_per_workspace, options))
         } else {
             None
         }
@@ -498,25 +935,36 @@ impl WindowTracker {
 		workspaces: &std::collections::BTreeMap<u64, Workspace>,
 		active_per_workspace: &std::collections::BTreeMap<u64, u64>,
 		last_focused_per_workspace: &std::collections::BTreeMap<u64, u64>,
-		filter_workspace: bool,
+		options: &SnapshotOptions,
 	) -> WindowSnapshot {
 		struct WindowWithWorkspace<'a> {
 		    window: &'a niri_ipc::Window,
 		    workspace: &'a Workspace,
 		}
 
+		// With `filter_workspace`, each output keeps its own active workspace;
+		// the bars narrow this down to their output afterwards.
 		let active_workspace_per_output: std::collections::HashMap<_, _> = workspaces
 		    .values()
 		    .filter(|ws| ws.is_active)
 		    .filter_map(|ws| ws.output.as_ref().map(|output| (output.clone(), ws.id)))
 		    .collect();
 
+		// Windows younger than `min_window_age` are left out until a later
+		// snapshot, so splash screens that close quickly never show up.
+		let old_enough = |window: &&niri_ipc::Window| {
+		    options.min_window_age.map_or(true, |min_age| {
+		        self.opened_at.get(&window.id).map_or(true, |at| at.elapsed() >= min_age)
+		    })
+		};
+
 		let mut window_workspace_pairs: Vec<_> = windows
 		    .values()
+		    .filter(old_enough)
 		    .filter_map(|window| {
 		        window.workspace_id.and_then(|ws_id| {
 		            workspaces.get(&ws_id).and_then(|ws| {
-		                if filter_workspace {
+		                if options.filter_workspace {
 		                    let is_active_on_output = ws.output.as_ref()
 		                        .and_then(|output| active_workspace_per_output.get(output))
 		                        .map(|active_ws_id| *active_ws_id == ws.id)
@@ -526,6 +974,11 @@ impl WindowTracker {
 		                        return None;
 		                    }
 		                }
+		                if let Some(visible) = &options.visible_workspaces {
+		                    if !ws.name.as_ref().is_some_and(|name| visible.contains(name)) {
+		                        return None;
+		                    }
+		                }
 		                Some(WindowWithWorkspace { window, workspace: ws })
 		            })
 		        })
@@ -554,9 +1007,19 @@ impl WindowTracker {
 			}
 		}
 
+		let floating_group = |pair: &WindowWithWorkspace| {
+			let is_floating = pair.window.layout.pos_in_scrolling_layout.is_none();
+			match options.floating_position {
+				FloatingPosition::Inline => 0,
+				FloatingPosition::Start => u8::from(!is_floating),
+				FloatingPosition::End => u8::from(is_floating),
+			}
+		};
+
 		window_workspace_pairs.sort_by(|a, b| {
-			a.workspace.idx
-				.cmp(&b.workspace.idx)
+			floating_group(a)
+				.cmp(&floating_group(b))
+				.then_with(|| a.workspace.idx.cmp(&b.workspace.idx))
 				.then_with(|| {
 				    let a_pos = a.window.layout.pos_in_scrolling_layout.or_else(|| position_map.get(&a.window.id).copied()).unwrap_or((usize::MAX, 0));
 				    let b_pos = b.window.layout.pos_in_scrolling_layout.or_else(|| position_map.get(&b.window.id).copied()).unwrap_or((usize::MAX, 0));
@@ -565,6 +1028,33 @@ impl WindowTracker {
 				.then_with(|| a.window.id.cmp(&b.window.id))
 		});
 
+		if options.order_mode == OrderMode::Mru {
+			let recency = |id: u64| self.focus_order.iter().position(|&known| known == id).unwrap_or(usize::MAX);
+			window_workspace_pairs.sort_by_key(|pair| recency(pair.window.id));
+		}
+
+		if options.order_mode == OrderMode::Title {
+			// Untitled windows sort last; ties keep their layout order.
+			let title_key = |pair: &WindowWithWorkspace| pair.window.title.as_deref().map(str::to_lowercase);
+			window_workspace_pairs.sort_by(|a, b| {
+				floating_group(a)
+					.cmp(&floating_group(b))
+					.then_with(|| a.workspace.idx.cmp(&b.workspace.idx))
+					.then_with(|| match (title_key(a), title_key(b)) {
+						(Some(a_title), Some(b_title)) => a_title.cmp(&b_title),
+						(a_title, b_title) => b_title.is_some().cmp(&a_title.is_some()),
+					})
+			});
+		}
+
+		// Weights come first in every order mode; the stable sort keeps the
+		// order above among windows of equal weight.
+		if let Some(weights) = &options.order_weights {
+			window_workspace_pairs.sort_by_key(|pair| {
+				weights.weight(pair.window.app_id.as_deref(), pair.window.title.as_deref()).unwrap_or(0)
+			});
+		}
+
         let active_workspace = workspaces.values().find(|ws| ws.is_active).map(|ws| ws.id);
         let overview_active = active_workspace.and_then(|ws_id| active_per_workspace.get(&ws_id).copied());
         let has_focused = window_workspace_pairs.iter().any(|pair| pair.window.is_focused);
@@ -589,14 +1079,19 @@ impl WindowTracker {
         window_workspace_pairs
             .into_iter()
             .map(|pair| {
-                let mut window_copy = pair.window.clone();
-                if !window_copy.is_focused && Some(window_copy.id) == highlight_window {
-                    tracing::info!("highlighting window {}", window_copy.id);
-                    window_copy.is_focused = true;
+                let is_active = !pair.window.is_focused && Some(pair.window.id) == highlight_window;
+                if is_active {
+                    tracing::info!("highlighting window {}", pair.window.id);
                 }
                 WindowInfo {
-                    inner: window_copy,
+                    inner: pair.window.clone(),
                     output_name: pair.workspace.output.clone(),
+                    workspace_name: pair.workspace.name.clone(),
+                    workspace_idx: pair.workspace.idx,
+                    workspace_active: pair.workspace.is_active,
+                    is_active,
+                    in_overview: self.overview_open,
+                    app_id_defaulted: false,
                 }
             })
             .collect()
@@ -609,12 +1104,67 @@ pub type WindowSnapshot = Vec<WindowInfo>;
 pub struct WindowInfo {
     inner: niri_ipc::Window,
     output_name: Option<String>,
+    workspace_name: Option<String>,
+    workspace_idx: u8,
+    workspace_active: bool,
+    is_active: bool,
+    in_overview: bool,
+    app_id_defaulted: bool,
 }
 
 impl WindowInfo {
     pub fn get_output(&self) -> Option<&str> {
         self.output_name.as_deref()
     }
+
+    pub fn get_workspace_name(&self) -> Option<&str> {
+        self.workspace_name.as_deref()
+    }
+
+    pub fn get_workspace_idx(&self) -> u8 {
+        self.workspace_idx
+    }
+
+    /// Whether the window's workspace is the one shown on its output.
+    pub fn is_on_active_workspace(&self) -> bool {
+        self.workspace_active
+    }
+
+    /// Set on the overview or last-focused window when nothing has real focus.
+    pub fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    /// Gives a window that has no app ID `app_id` instead.
+    pub fn apply_default_app_id(&mut self, app_id: &str) {
+        if self.inner.app_id.is_none() {
+            self.inner.app_id = Some(app_id.to_string());
+            self.app_id_defaulted = true;
+        }
+    }
+
+    /// The app ID niri reported, ignoring `default_app_id`.
+    pub fn reported_app_id(&self) -> Option<&str> {
+        if self.app_id_defaulted {
+            None
+        } else {
+            self.inner.app_id.as_deref()
+        }
+    }
+
+    /// Whether niri's overview was open when the snapshot was taken.
+    pub fn in_overview(&self) -> bool {
+        self.in_overview
+    }
+
+    pub fn is_minimized(&self, minimize_workspace: &str) -> bool {
+        self.get_workspace_name() == Some(minimize_workspace)
+    }
+
+    /// Floating windows have no position in the scrolling layout.
+    pub fn is_floating(&self) -> bool {
+        self.layout.pos_in_scrolling_layout.is_none()
+    }
 }
 
 impl Deref for WindowInfo {
@@ -624,3 +1174,27 @@ impl Deref for WindowInfo {
         &self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::target_column_index;
+
+    #[test]
+    fn unstacked_column_moves_by_delta() {
+        assert_eq!(target_column_index(3, false, 2), 5);
+        assert_eq!(target_column_index(3, false, -2), 1);
+    }
+
+    #[test]
+    fn stacked_window_starts_right_of_its_column() {
+        assert_eq!(target_column_index(3, true, 2), 6);
+        assert_eq!(target_column_index(3, true, -2), 2);
+    }
+
+    #[test]
+    fn index_is_clamped_to_first_column() {
+        assert_eq!(target_column_index(2, false, -5), 1);
+        assert_eq!(target_column_index(1, true, -10), 1);
+        assert_eq!(target_column_index(1, false, 0), 1);
+    }
+}
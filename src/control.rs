@@ -0,0 +1,233 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    sync::{Mutex, Once, OnceLock, mpsc},
+    time::{Duration, Instant},
+};
+use async_channel::{Receiver, Sender};
+use serde_json::{Value, json};
+
+/// How long the socket waits for the bars to answer a command.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Every module instance in the process (one per bar) gets each command.
+static SUBSCRIBERS: Mutex<Vec<Sender<ControlRequest>>> = Mutex::new(Vec::new());
+static LISTENER: Once = Once::new();
+static DBUS_CONNECTION: OnceLock<zbus::blocking::Connection> = OnceLock::new();
+static DBUS: Once = Once::new();
+
+const DBUS_NAME: &str = "org.niri_window_buttons";
+const DBUS_PATH: &str = "/org/niri_window_buttons";
+
+#[derive(Debug, Clone)]
+pub enum ControlCommand {
+    ScrollToApp(String),
+    ShowIndexOverlay,
+    FocusIndex(usize),
+    GetWindows,
+    Refresh,
+}
+
+impl ControlCommand {
+    fn parse(line: &str) -> Result<Self, String> {
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("scroll-to-app"), Some(app_id), None) => Ok(Self::ScrollToApp(app_id.to_string())),
+            (Some("scroll-to-app"), _, _) => Err("usage: scroll-to-app <app_id>".to_string()),
+            (Some("show-index-overlay"), None, _) => Ok(Self::ShowIndexOverlay),
+            (Some("focus-index"), Some(index), None) => match index.parse() {
+                Ok(index) if index > 0 => Ok(Self::FocusIndex(index)),
+                _ => Err(format!("invalid index: {index}")),
+            },
+            (Some("focus-index"), _, _) => Err("usage: focus-index <n>".to_string()),
+            (Some("get-windows"), None, _) => Ok(Self::GetWindows),
+            (Some("refresh"), None, _) => Ok(Self::Refresh),
+            (Some(other), _, _) => Err(format!("unknown command: {other}")),
+            (None, _, _) => Err("empty command".to_string()),
+        }
+    }
+
+    /// Merges the answers of all bars into the single reply sent to the client.
+    fn combine(&self, replies: &[Value]) -> Value {
+        match self {
+            Self::ScrollToApp(_) | Self::FocusIndex(_) => {
+                json!({ "found": replies.iter().any(|reply| reply["found"] == true) })
+            }
+            Self::ShowIndexOverlay => json!({ "visible": replies.iter().any(|reply| reply["visible"] == true) }),
+            Self::Refresh => json!({ "refreshed": !replies.is_empty() }),
+            Self::GetWindows => {
+                // Bars showing all outputs list the same windows.
+                let mut seen = std::collections::HashSet::new();
+                let windows: Vec<Value> = replies.iter()
+                    .filter_map(|reply| reply["windows"].as_array())
+                    .flatten()
+                    .filter(|window| seen.insert(window["id"].as_u64()))
+                    .cloned()
+                    .collect();
+                json!({ "windows": windows })
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    reply: mpsc::Sender<Value>,
+}
+
+impl ControlRequest {
+    pub fn respond(self, reply: Value) {
+        // The client may have given up waiting already.
+        let _ = self.reply.send(reply);
+    }
+}
+
+/// Returns the commands addressed to the calling module instance, from the
+/// socket and DBus alike.
+pub fn subscribe() -> Receiver<ControlRequest> {
+    let (tx, rx) = async_channel::unbounded();
+    SUBSCRIBERS.lock().expect("control subscribers lock").push(tx);
+    rx
+}
+
+/// Starts the control socket, once per process.
+pub fn start_socket() {
+    LISTENER.call_once(|| {
+        let Some(path) = socket_path() else {
+            tracing::warn!("XDG_RUNTIME_DIR is not set, control socket disabled");
+            return;
+        };
+
+        std::thread::spawn(move || {
+            if let Err(e) = run_listener(&path) {
+                tracing::error!(%e, ?path, "control socket failed");
+            }
+        });
+    });
+}
+
+/// Claims `org.niri_window_buttons` on the session bus and serves the control
+/// commands there, once per process. Only one Waybar can own the name; the
+/// others log the failure and keep working without it.
+pub fn start_dbus() {
+    DBUS.call_once(|| {
+        std::thread::spawn(|| {
+            let connection = zbus::blocking::connection::Builder::session()
+                .and_then(|builder| builder.name(DBUS_NAME))
+                .and_then(|builder| builder.serve_at(DBUS_PATH, DbusControl))
+                .and_then(|builder| builder.build());
+            match connection {
+                Ok(connection) => {
+                    tracing::info!(name = DBUS_NAME, "DBus control listening");
+                    let _ = DBUS_CONNECTION.set(connection);
+                }
+                Err(e) => tracing::error!(%e, name = DBUS_NAME, "DBus control failed"),
+            }
+        });
+    });
+}
+
+/// The control commands as DBus methods. Calls wait for the bars like socket
+/// clients do, so each runs on the connection's own thread.
+struct DbusControl;
+
+#[zbus::interface(name = "org.niri_window_buttons.Control")]
+impl DbusControl {
+    fn scroll_to_app(&self, app_id: String) -> bool {
+        dispatch(ControlCommand::ScrollToApp(app_id))["found"] == true
+    }
+
+    fn show_index_overlay(&self) -> bool {
+        dispatch(ControlCommand::ShowIndexOverlay)["visible"] == true
+    }
+
+    fn focus_index(&self, index: u32) -> zbus::fdo::Result<bool> {
+        if index == 0 {
+            return Err(zbus::fdo::Error::InvalidArgs("indices start at 1".to_string()));
+        }
+        Ok(dispatch(ControlCommand::FocusIndex(index as usize))["found"] == true)
+    }
+
+    /// The `get-windows` reply's window list, as a JSON string.
+    fn get_windows(&self) -> String {
+        dispatch(ControlCommand::GetWindows)["windows"].to_string()
+    }
+
+    fn refresh(&self) -> bool {
+        dispatch(ControlCommand::Refresh)["refreshed"] == true
+    }
+}
+
+fn socket_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join("niri_window_buttons.sock"))
+}
+
+fn run_listener(path: &PathBuf) -> io::Result<()> {
+    // A socket that still accepts connections belongs to another running
+    // Waybar; only a stale one left behind by a crash may be replaced.
+    if UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AddrInUse, "another instance is already listening"));
+    }
+
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    let listener = UnixListener::bind(path)?;
+    tracing::info!(?path, "control socket listening");
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle_client(stream) {
+                    tracing::warn!(%e, "control client failed");
+                }
+            }
+            Err(e) => tracing::warn!(%e, "failed to accept control client"),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_client(mut stream: UnixStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let reply = match ControlCommand::parse(line.trim()) {
+        Ok(command) => dispatch(command),
+        Err(message) => json!({ "error": message }),
+    };
+
+    writeln!(stream, "{reply}")
+}
+
+fn dispatch(command: ControlCommand) -> Value {
+    let (reply_tx, reply_rx) = mpsc::channel();
+
+    let mut subscribers = SUBSCRIBERS.lock().expect("control subscribers lock");
+    subscribers.retain(|subscriber| {
+        let request = ControlRequest { command: command.clone(), reply: reply_tx.clone() };
+        subscriber.send_blocking(request).is_ok()
+    });
+    let expected = subscribers.len();
+    drop(subscribers);
+    drop(reply_tx);
+
+    let deadline = Instant::now() + REPLY_TIMEOUT;
+    let mut replies = Vec::with_capacity(expected);
+    while replies.len() < expected {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match reply_rx.recv_timeout(remaining) {
+            Ok(reply) => replies.push(reply),
+            Err(_) => break,
+        }
+    }
+
+    command.combine(&replies)
+}
@@ -16,6 +16,9 @@ pub enum ModuleError {
 
     #[error("window snapshot channel closed")]
     SnapshotChannelClosed,
+
+    #[error("no output named {0}")]
+    UnknownOutput(String),
 }
 
 impl ModuleError {
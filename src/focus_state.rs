@@ -0,0 +1,86 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+};
+use serde::Serialize;
+use waybar_cffi::gtk::{gio, glib};
+use crate::compositor::WindowInfo;
+
+/// The state most recently handed to `publish`, shared by every module
+/// instance in the process (one per bar) so each change is written once.
+static LATEST: Mutex<Option<FocusState>> = Mutex::new(None);
+/// Keeps writes in order, since they run on gio's thread pool.
+static WRITE: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FocusState {
+    app_id: Option<String>,
+    title: Option<String>,
+    workspace: Option<u64>,
+    workspace_idx: Option<u8>,
+    workspace_name: Option<String>,
+}
+
+impl FocusState {
+    pub fn from_window(window: Option<&WindowInfo>) -> Self {
+        window
+            .map(|w| Self {
+                app_id: w.reported_app_id().map(str::to_string),
+                title: w.title.clone(),
+                workspace: w.workspace_id,
+                workspace_idx: w.workspace_id.map(|_| w.get_workspace_idx()),
+                workspace_name: w.get_workspace_name().map(str::to_string),
+            })
+            .unwrap_or_default()
+    }
+
+    /// Writes this state to `path` off the main loop, unless another bar
+    /// already published it.
+    pub fn publish(self, path: &Path) {
+        {
+            let mut latest = LATEST.lock().expect("focus state lock");
+            if latest.as_ref() == Some(&self) {
+                return;
+            }
+            *latest = Some(self.clone());
+        }
+
+        let path = path.to_path_buf();
+        glib::spawn_future_local(async move {
+            let target = path.clone();
+            match gio::spawn_blocking(move || self.write_if_latest(&target)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::warn!(%e, ?path, "failed to write focus state file"),
+                Err(_) => tracing::error!("task spawning error"),
+            }
+        });
+    }
+
+    /// Skips the write when a newer state was published meanwhile, so a
+    /// slow write can't overwrite a later one.
+    fn write_if_latest(&self, path: &Path) -> io::Result<()> {
+        let _write = WRITE.lock().expect("focus state write lock");
+        if LATEST.lock().expect("focus state lock").as_ref() != Some(self) {
+            return Ok(());
+        }
+        self.write_atomic(path)
+    }
+
+    /// Replaces the file at `path` with this state as a single JSON line, via a
+    /// temporary sibling file so readers never observe a partial write.
+    fn write_atomic(&self, path: &Path) -> io::Result<()> {
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    }
+}
@@ -1,12 +1,13 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
 use async_channel::Sender;
 use futures::{Stream, StreamExt};
-use waybar_cffi::gtk::glib;
+use waybar_cffi::gtk::{gdk, glib};
 use crate::{
     compositor::{CompositorClient, WindowSnapshot, WorkspaceEventStream},
+    control::{self, ControlRequest},
     icons::IconResolver,
     notifications::{self, NotificationData},
-    settings::Settings,
+    settings::{Settings, WindowAction},
 };
 
 #[derive(Debug, Clone)]
@@ -17,6 +18,8 @@ struct StateInner {
     settings: Settings,
     icon_resolver: IconResolver,
     compositor: CompositorClient,
+    minimized_origins: Mutex<HashMap<u64, u64>>,
+    last_action: Mutex<Option<(u64, WindowAction, Instant)>>,
 }
 
 impl SharedState {
@@ -24,6 +27,8 @@ impl SharedState {
         Self(Arc::new(StateInner {
             compositor: CompositorClient::create(settings.clone()),
             icon_resolver: IconResolver::new(),
+            minimized_origins: Mutex::new(HashMap::new()),
+            last_action: Mutex::new(None),
             settings,
         }))
     }
@@ -40,6 +45,31 @@ impl SharedState {
         &self.0.compositor
     }
 
+    pub fn remember_minimized_origin(&self, window_id: u64, workspace_id: u64) {
+        self.0.minimized_origins.lock().expect("minimized origins lock").insert(window_id, workspace_id);
+    }
+
+    pub fn take_minimized_origin(&self, window_id: u64) -> Option<u64> {
+        self.0.minimized_origins.lock().expect("minimized origins lock").remove(&window_id)
+    }
+
+    /// Records `action` on `window_id` and reports whether the same action hit
+    /// the same window within `action_cooldown_ms`, e.g. from a double-fired
+    /// click, in which case it should be dropped.
+    pub fn should_throttle(&self, window_id: u64, action: &WindowAction) -> bool {
+        let cooldown = Duration::from_millis(self.settings().action_cooldown_ms());
+        let now = Instant::now();
+        let mut last_action = self.0.last_action.lock().expect("last action lock");
+
+        let throttled = last_action.as_ref().is_some_and(|(last_id, last, at)| {
+            *last_id == window_id && last == action && now.duration_since(*at) < cooldown
+        });
+        if !throttled {
+            *last_action = Some((window_id, action.clone(), now));
+        }
+        throttled
+    }
+
     pub fn create_event_stream(&self) -> impl Stream<Item = EventMessage> {
         let (tx, rx) = async_channel::unbounded();
 
@@ -48,7 +78,17 @@ impl SharedState {
         }
 
         glib::spawn_future_local(forward_window_updates(tx.clone(), self.compositor().create_window_stream()));
-        glib::spawn_future_local(forward_workspace_changes(tx, self.compositor().create_workspace_stream()));
+        glib::spawn_future_local(forward_workspace_changes(tx.clone(), self.compositor().create_workspace_stream()));
+        if self.settings().control_socket() {
+            control::start_socket();
+        }
+        if self.settings().dbus_control() {
+            control::start_dbus();
+        }
+        if self.settings().control_socket() || self.settings().dbus_control() {
+            glib::spawn_future_local(forward_control_requests(tx.clone(), control::subscribe()));
+        }
+        forward_monitor_changes(tx);
 
         async_stream::stream! {
             while let Ok(event) = rx.recv().await {
@@ -62,6 +102,8 @@ pub enum EventMessage {
     Notification(Box<NotificationData>),
     WindowUpdate(WindowSnapshot),
     Workspaces(()),
+    MonitorsChanged,
+    Control(ControlRequest),
 }
 
 async fn forward_notifications(tx: Sender<EventMessage>) {
@@ -88,3 +130,34 @@ async fn forward_workspace_changes(tx: Sender<EventMessage>, stream: WorkspaceEv
         }
     }
 }
+
+async fn forward_control_requests(tx: Sender<EventMessage>, requests: async_channel::Receiver<ControlRequest>) {
+    while let Ok(request) = requests.recv().await {
+        if let Err(e) = tx.send(EventMessage::Control(request)).await {
+            tracing::error!(%e, "failed to forward control request");
+        }
+    }
+}
+
+/// Reports monitors being plugged in or removed, which niri doesn't announce
+/// through a workspace event.
+fn forward_monitor_changes(tx: Sender<EventMessage>) {
+    let Some(display) = gdk::Display::default() else {
+        tracing::warn!("no default display, monitor hotplug will not be tracked");
+        return;
+    };
+
+    let added_tx = tx.clone();
+    display.connect_monitor_added(move |_, monitor| {
+        tracing::info!(model = ?monitor.model(), "monitor added");
+        if let Err(e) = added_tx.try_send(EventMessage::MonitorsChanged) {
+            tracing::error!(%e, "failed to forward monitor change");
+        }
+    });
+    display.connect_monitor_removed(move |_, monitor| {
+        tracing::info!(model = ?monitor.model(), "monitor removed");
+        if let Err(e) = tx.try_send(EventMessage::MonitorsChanged) {
+            tracing::error!(%e, "failed to forward monitor change");
+        }
+    });
+}
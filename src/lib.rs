@@ -1,22 +1,29 @@
 use std::{
+    cell::Cell,
     collections::{BTreeMap, BTreeSet, HashMap},
+    rc::Rc,
     sync::{Arc, LazyLock, Mutex},
+    time::Instant,
 };
 
 use futures::StreamExt;
+use itertools::Itertools;
 use settings::Settings;
 use tracing_subscriber::{EnvFilter, fmt::format::FmtSpan};
 use waybar_cffi::{
     Module,
-    gtk::{self, Orientation, ReliefStyle, ScrolledWindow, gio, glib::MainContext, traits::{AdjustmentExt, BoxExt, ButtonExt, ContainerExt, ScrolledWindowExt, StyleContextExt, WidgetExt}},
+    gtk::{self, Orientation, ReliefStyle, ScrolledWindow, gio, glib::MainContext, prelude::Cast, traits::{AdjustmentExt, BoxExt, ButtonExt, ContainerExt, LabelExt, ScrolledWindowExt, StyleContextExt, WidgetExt}},
     waybar_module,
 };
 
 mod compositor;
+mod control;
 mod errors;
+mod focus_state;
 mod global;
 mod icons;
 mod notifications;
+mod persisted_order;
 mod screen;
 mod settings;
 mod system;
@@ -24,8 +31,10 @@ mod widget;
 
 use compositor::{WindowInfo, WindowSnapshot};
 use errors::ModuleError;
+use focus_state::FocusState;
 use global::{EventMessage, SharedState};
 use notifications::NotificationData;
+use persisted_order::PersistedOrder;
 use system::ProcessInfo;
 use widget::WindowButton;
 
@@ -62,6 +71,7 @@ waybar_module!(WindowButtonsModule);
 
 async fn initialize_module(info: &waybar_cffi::InitInfo, state: SharedState) -> Result<(), ModuleError> {
     let root = info.get_root_widget();
+    widget::load_stylesheet(state.settings().stylesheet());
 
     let main_container = gtk::Box::new(Orientation::Horizontal, 0);
 
@@ -70,47 +80,129 @@ async fn initialize_module(info: &waybar_cffi::InitInfo, state: SharedState) ->
     left_arrow.set_relief(ReliefStyle::None);
     left_arrow.style_context().add_class("scroll-arrow");
     left_arrow.style_context().add_class("scroll-arrow-left");
+    widget::add_module_styles(&left_arrow);
     left_arrow.set_sensitive(false);
     left_arrow.set_no_show_all(true);
     left_arrow.hide();
     
+    let scroll_indicator = state.settings().scroll_indicator();
     let scrolled = ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
-    scrolled.set_policy(gtk::PolicyType::External, gtk::PolicyType::Never);
-    scrolled.set_overlay_scrolling(false);
+    if scroll_indicator == settings::ScrollIndicator::Scrollbar {
+        scrolled.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Never);
+        scrolled.set_overlay_scrolling(true);
+    } else {
+        scrolled.set_policy(gtk::PolicyType::External, gtk::PolicyType::Never);
+        scrolled.set_overlay_scrolling(false);
+    }
     scrolled.set_propagate_natural_width(false);
 
+    let last_manual_scroll: Rc<Cell<Option<Instant>>> = Rc::new(Cell::new(None));
+
+    main_container.set_size_request(-1, -1);
+
+    let button_container = gtk::Box::new(Orientation::Horizontal, 0);
+    button_container.style_context().add_class("niri-window-buttons");
+    widget::add_module_styles(&button_container);
+    button_container.connect_key_press_event(|container, event| {
+        use waybar_cffi::gtk::gdk::keys::constants as key;
+
+        let keyval = event.keyval();
+        let step: i32 = if keyval == key::Left || keyval == key::KP_Left {
+            -1
+        } else if keyval == key::Right || keyval == key::KP_Right {
+            1
+        } else {
+            return gtk::glib::Propagation::Proceed;
+        };
+
+        let children = container.children();
+        let Some(current) = container.focus_child() else {
+            return gtk::glib::Propagation::Proceed;
+        };
+        let Some(index) = children.iter().position(|child| *child == current) else {
+            return gtk::glib::Propagation::Proceed;
+        };
+
+        let target = index as i32 + step;
+        if let Some(next) = usize::try_from(target).ok().and_then(|i| children.get(i)) {
+            // Window buttons are wrapped with their close button, so focus the
+            // first focusable widget inside rather than the child itself.
+            next.child_focus(gtk::DirectionType::TabForward);
+        }
+        gtk::glib::Propagation::Stop
+    });
+    scrolled.add(&button_container);
+
     let scrolled_clone = scrolled.clone();
+    let scroll_marker = last_manual_scroll.clone();
+    let scroll_state = state.clone();
+    let scroll_container = button_container.clone();
+    // Touchpads send many small smooth deltas; a workspace switch waits
+    // until they add up to one wheel notch.
+    let workspace_scroll = Cell::new(0.0);
     scrolled.connect_scroll_event(move |_, event| {
         use waybar_cffi::gtk::gdk::ScrollDirection;
-        
+
+        // Negative scrolls towards the start (up/left), in wheel notches.
+        let amount = match event.direction() {
+            ScrollDirection::Up | ScrollDirection::Left => -1.0,
+            ScrollDirection::Down | ScrollDirection::Right => 1.0,
+            ScrollDirection::Smooth => {
+                let (dx, dy) = event.delta();
+                if dy != 0.0 { dy } else { dx }
+            }
+            _ => return gtk::glib::Propagation::Proceed,
+        };
+
+        if scroll_state.settings().scroll_empty_switches_workspace()
+            && !pointer_over_child(&scroll_container, event.root())
+        {
+            let total = workspace_scroll.get() + amount;
+            if total.abs() >= 1.0 {
+                workspace_scroll.set(0.0);
+                if let Err(e) = scroll_state.compositor().focus_workspace_relative(total < 0.0) {
+                    tracing::warn!(%e, "workspace switch failed");
+                }
+            } else {
+                workspace_scroll.set(total);
+            }
+            return gtk::glib::Propagation::Stop;
+        }
+
         let hadj = scrolled_clone.hadjustment();
         let step = hadj.page_size() / 4.0;
-        
-        match event.direction() {
-           ScrollDirection::Up | ScrollDirection::Left => {
-               hadj.set_value((hadj.value() - step).max(0.0));
-               gtk::glib::Propagation::Stop
-           }
-           ScrollDirection::Down | ScrollDirection::Right => {
-               let max = hadj.upper() - hadj.page_size();
-               hadj.set_value((hadj.value() + step).min(max));
-               gtk::glib::Propagation::Stop
-           }
-           _ => gtk::glib::Propagation::Proceed
-        }
+        let max = (hadj.upper() - hadj.page_size()).max(0.0);
+        scroll_marker.set(Some(Instant::now()));
+        hadj.set_value((hadj.value() + amount * step).clamp(0.0, max));
+        gtk::glib::Propagation::Stop
     });
 
-    main_container.set_size_request(-1, -1);
+    let empty_area_state = state.clone();
+    let empty_area_container = button_container.clone();
+    scrolled.add_events(gtk::gdk::EventMask::BUTTON_PRESS_MASK);
+    scrolled.connect_button_press_event(move |_, event| {
+        let on_button = pointer_over_child(&empty_area_container, event.root());
+        if on_button || event.event_type() != gtk::gdk::EventType::ButtonPress {
+            return gtk::glib::Propagation::Proceed;
+        }
 
-    let button_container = gtk::Box::new(Orientation::Horizontal, 0);
-    button_container.style_context().add_class("niri-window-buttons");
-    scrolled.add(&button_container);
+        let actions = empty_area_state.settings().empty_area_actions();
+        let action = match event.button() {
+            1 => &actions.left_click,
+            2 => &actions.middle_click,
+            3 => &actions.right_click,
+            _ => return gtk::glib::Propagation::Proceed,
+        };
+        widget::run_empty_area_action(&empty_area_state, action);
+        gtk::glib::Propagation::Stop
+    });
     
     let right_arrow = gtk::Button::new();
     right_arrow.set_label(state.settings().scroll_arrow_right());
     right_arrow.set_relief(ReliefStyle::None);
     right_arrow.style_context().add_class("scroll-arrow");
     right_arrow.style_context().add_class("scroll-arrow-right");
+    widget::add_module_styles(&right_arrow);
     right_arrow.set_sensitive(false);
     right_arrow.set_no_show_all(true);
     right_arrow.hide();
@@ -123,10 +215,12 @@ async fn initialize_module(info: &waybar_cffi::InitInfo, state: SharedState) ->
    
     let hadj = scrolled.hadjustment();
     
+    let invert_arrows = state.settings().invert_scroll_arrows();
     let update_arrows = {
         let hadj = hadj.clone();
         let left_arrow = left_arrow.clone();
         let right_arrow = right_arrow.clone();
+        let button_container = button_container.clone();
         
         move || {
             let value = hadj.value();
@@ -145,52 +239,126 @@ async fn initialize_module(info: &waybar_cffi::InitInfo, state: SharedState) ->
                 let max_scroll = upper - page_size;
                 let at_end = value >= max_scroll - 0.5;
                 
-                left_arrow.set_sensitive(!at_start);
-                right_arrow.set_sensitive(!at_end);
+                if invert_arrows {
+                    left_arrow.set_sensitive(!at_end);
+                    right_arrow.set_sensitive(!at_start);
+                } else {
+                    left_arrow.set_sensitive(!at_start);
+                    right_arrow.set_sensitive(!at_end);
+                }
+            }
+
+            // Mark the arrow that scrolls towards an urgent button out of view.
+            let (mut urgent_before, mut urgent_after) = (false, false);
+            if has_overflow {
+                for child in button_container.children() {
+                    if !child.style_context().has_class("urgent") {
+                        continue;
+                    }
+                    let allocation = child.allocation();
+                    let x = allocation.x() as f64;
+                    urgent_before |= x + allocation.width() as f64 <= value + 0.5;
+                    urgent_after |= x >= value + page_size - 0.5;
+                }
+            }
+            let (left_urgent, right_urgent) = if invert_arrows {
+                (urgent_after, urgent_before)
+            } else {
+                (urgent_before, urgent_after)
+            };
+            for (arrow, urgent) in [(&left_arrow, left_urgent), (&right_arrow, right_urgent)] {
+                if urgent {
+                    arrow.style_context().add_class("arrow-has-urgent");
+                } else {
+                    arrow.style_context().remove_class("arrow-has-urgent");
+                }
             }
         }
     };
     
-    let update_on_changed = update_arrows.clone();
-    hadj.connect_changed(move |_| {
-        let update = update_on_changed.clone();
-        gtk::glib::idle_add_local_once(move || {
-            update();
+    // The arrows stay hidden unless they are the chosen scroll indicator.
+    if scroll_indicator == settings::ScrollIndicator::Arrows {
+        let update_on_changed = update_arrows.clone();
+        hadj.connect_changed(move |_| {
+            let update = update_on_changed.clone();
+            gtk::glib::idle_add_local_once(move || {
+                update();
+            });
         });
-    });
 
-    let update_on_value = update_arrows.clone();
-    hadj.connect_value_changed(move |_| {
-        let update = update_on_value.clone();
-        gtk::glib::idle_add_local_once(move || {
-            update();
+        let update_on_value = update_arrows.clone();
+        hadj.connect_value_changed(move |_| {
+            let update = update_on_value.clone();
+            gtk::glib::idle_add_local_once(move || {
+                update();
+            });
         });
-    });
+
+        // Urgency comes and goes without any scrolling, so follow the
+        // buttons' classes too.
+        let update_on_add = update_arrows.clone();
+        button_container.connect_add(move |_, child| {
+            let update = update_on_add.clone();
+            child.style_context().connect_changed(move |_| {
+                let update = update.clone();
+                gtk::glib::idle_add_local_once(move || {
+                    update();
+                });
+            });
+        });
+    }
     
     let hadj_left = hadj.clone();
+    let scroll_marker = last_manual_scroll.clone();
     left_arrow.connect_clicked(move |_| {
-        let current = hadj_left.value();
-        let target = (current - hadj_left.page_size()).max(0.0);
-        smooth_scroll_to(&hadj_left, target);
+        scroll_marker.set(Some(Instant::now()));
+        scroll_by_page(&hadj_left, if invert_arrows { 1.0 } else { -1.0 });
     });
     
     let hadj_right = hadj.clone();
+    let scroll_marker = last_manual_scroll.clone();
     right_arrow.connect_clicked(move |_| {
-        let current = hadj_right.value();
-        let max = hadj_right.upper() - hadj_right.page_size();
-        let target = (current + hadj_right.page_size()).min(max);
-        smooth_scroll_to(&hadj_right, target);
+        scroll_marker.set(Some(Instant::now()));
+        scroll_by_page(&hadj_right, if invert_arrows { -1.0 } else { 1.0 });
     });
 
     let context = MainContext::default();
     let main_container_clone = main_container.clone();
     context.spawn_local(async move {
-        ModuleInstance::create(state, button_container, scrolled, main_container_clone).run_event_loop().await
+        ModuleInstance::create(state, button_container, scrolled, main_container_clone, last_manual_scroll).run_event_loop().await
     });
 
     Ok(())
 }
 
+/// Whether the root coordinates `(x, y)` of an event fall on one of
+/// `container`'s visible children. Goes by allocations rather than hover
+/// state, which lags behind the pointer and sticks during grabs.
+fn pointer_over_child(container: &gtk::Box, (x, y): (f64, f64)) -> bool {
+    let Some(window) = container.window() else {
+        return false;
+    };
+    let (_, origin_x, origin_y) = window.origin();
+    let (x, y) = (x as i32 - origin_x, y as i32 - origin_y);
+
+    container.children().iter()
+        .filter(|child| child.is_visible())
+        .any(|child| {
+            let allocation = child.allocation();
+            x >= allocation.x()
+                && y >= allocation.y()
+                && x < allocation.x() + allocation.width()
+                && y < allocation.y() + allocation.height()
+        })
+}
+
+/// Scrolls one page towards the end (`direction > 0`) or the start.
+fn scroll_by_page(adjustment: &gtk::Adjustment, direction: f64) {
+    let max = adjustment.upper() - adjustment.page_size();
+    let target = (adjustment.value() + direction * adjustment.page_size()).clamp(0.0, max.max(0.0));
+    smooth_scroll_to(adjustment, target);
+}
+
 fn smooth_scroll_to(adjustment: &gtk::Adjustment, target: f64) {
     let start = adjustment.value();
     let distance = target - start;
@@ -233,11 +401,25 @@ struct ModuleInstance {
     main_container: gtk::Box,
     previous_snapshot: Option<WindowSnapshot>,
     current_output: Option<String>,
+    persisted_order: Option<PersistedOrder>,
+    last_manual_scroll: Rc<Cell<Option<Instant>>>,
+    index_overlay_visible: bool,
+    focus_recency: Vec<u64>,
+    overflow: Option<widget::OverflowButton>,
+    pinned_buttons: BTreeMap<String, widget::PinnedButton>,
+    workspace_headers: Vec<gtk::Label>,
+    empty_placeholder: Option<gtk::Label>,
     state: SharedState,
 }
 
 impl ModuleInstance {
-    fn create(state: SharedState, container: gtk::Box, scrolled_window: ScrolledWindow, main_container: gtk::Box) -> Self {
+    fn create(
+        state: SharedState,
+        container: gtk::Box,
+        scrolled_window: ScrolledWindow,
+        main_container: gtk::Box,
+        last_manual_scroll: Rc<Cell<Option<Instant>>>,
+    ) -> Self {
         Self {
             buttons: BTreeMap::new(),
             container,
@@ -245,6 +427,14 @@ impl ModuleInstance {
             main_container,
             previous_snapshot: None,
             current_output: None,
+            persisted_order: None,
+            last_manual_scroll,
+            index_overlay_visible: false,
+            focus_recency: Vec::new(),
+            overflow: None,
+            pinned_buttons: BTreeMap::new(),
+            workspace_headers: Vec::new(),
+            empty_placeholder: None,
             state,
         }
     }
@@ -269,17 +459,154 @@ impl ModuleInstance {
                         changed
                     };
 
+                    // Re-render with the bar's own output filter. Snapshots with
+                    // `only_current_workspace` already hold the active workspace
+                    // of every output, so showing them unfiltered here filled
+                    // each bar with the other outputs' windows.
                     if filter_changed && self.update_output_and_resize().await {
                         if let Some(snapshot) = self.previous_snapshot.clone() {
-                            let filter = Arc::new(Mutex::new(screen::DisplayFilter::ShowAll));
-                            self.handle_window_update(snapshot, filter).await;
+                            self.handle_window_update(snapshot, display_filter.clone()).await;
                         }
                     }
                 }
+                EventMessage::MonitorsChanged => {
+                    // The bar may now sit on a different output, so always
+                    // re-render rather than only when the filter changed.
+                    let updated_filter = self.determine_display_filter().await;
+                    *display_filter.lock().expect("display filter lock") = updated_filter;
+                    self.update_output_and_resize().await;
+
+                    if let Some(snapshot) = self.previous_snapshot.clone() {
+                        self.handle_window_update(snapshot, display_filter.clone()).await;
+                    }
+                }
+                EventMessage::Control(request) => self.handle_control(request, display_filter.clone()).await,
             }
         }
     }
 
+    async fn handle_control(&mut self, request: control::ControlRequest, filter: Arc<Mutex<screen::DisplayFilter>>) {
+        let reply = match &request.command {
+            control::ControlCommand::ScrollToApp(app_id) => {
+                serde_json::json!({ "found": self.scroll_to_app(app_id) })
+            }
+            control::ControlCommand::ShowIndexOverlay => {
+                self.index_overlay_visible = !self.index_overlay_visible;
+                self.refresh_index_overlay();
+                serde_json::json!({ "visible": self.index_overlay_visible })
+            }
+            control::ControlCommand::FocusIndex(index) => {
+                serde_json::json!({ "found": self.focus_index(*index) })
+            }
+            control::ControlCommand::GetWindows => {
+                serde_json::json!({ "windows": self.window_list() })
+            }
+            control::ControlCommand::Refresh => {
+                self.rebuild(filter).await;
+                serde_json::json!({ "refreshed": true })
+            }
+        };
+        request.respond(reply);
+    }
+
+    /// The windows this bar currently has buttons for, after ignore rules and
+    /// output filtering, in snapshot order.
+    fn window_list(&self) -> Vec<serde_json::Value> {
+        let Some(snapshot) = &self.previous_snapshot else {
+            return Vec::new();
+        };
+
+        snapshot.iter()
+            .filter(|window| self.buttons.contains_key(&window.id))
+            .map(|window| serde_json::json!({
+                "id": window.id,
+                "app_id": window.app_id,
+                "title": window.title,
+                "output": window.get_output(),
+                "workspace": window.get_workspace_idx(),
+                "workspace_id": window.workspace_id,
+                "focused": window.is_focused,
+            }))
+            .collect()
+    }
+
+    /// Buttons in the order they are shown, which is what indices refer to.
+    fn visible_buttons(&self) -> Vec<&WindowButton> {
+        self.container.children().iter()
+            .filter_map(|child| {
+                self.buttons.values().find(|button| button.get_widget().upcast_ref::<gtk::Widget>() == child)
+            })
+            .collect()
+    }
+
+    fn refresh_index_overlay(&self) {
+        for (position, button) in self.visible_buttons().into_iter().enumerate() {
+            button.set_index_overlay(self.index_overlay_visible.then_some(position + 1));
+        }
+    }
+
+    /// Focuses the button at the 1-based `index`. With a bar per output only
+    /// the bar on the focused output acts, so each index means one window.
+    fn focus_index(&self, index: usize) -> bool {
+        if let Some(current) = self.current_output.as_deref() {
+            match self.state.compositor().focused_output_name() {
+                Ok(Some(focused)) if focused != current => return false,
+                Ok(_) => {}
+                Err(e) => tracing::warn!(%e, "failed to query focused output"),
+            }
+        }
+
+        let Some(button) = self.visible_buttons().get(index - 1).copied() else {
+            return false;
+        };
+        button.run_action(&settings::WindowAction::FocusWindow);
+        true
+    }
+
+    /// Scrolls the strip so the first button of `app_id` is fully visible.
+    fn scroll_to_app(&self, app_id: &str) -> bool {
+        let Some(widget) = self.container.children().into_iter().find(|child| {
+            self.buttons.values().any(|button| {
+                button.app_id() == Some(app_id) && button.get_widget().upcast_ref::<gtk::Widget>() == child
+            })
+        }) else {
+            return false;
+        };
+
+        let allocation = widget.allocation();
+        let hadj = self.scrolled_window.hadjustment();
+        let button_x = allocation.x() as f64;
+        let button_right = button_x + allocation.width() as f64;
+
+        if button_x < hadj.value() {
+            smooth_scroll_to(&hadj, button_x);
+        } else if button_right > hadj.value() + hadj.page_size() {
+            smooth_scroll_to(&hadj, button_right - hadj.page_size());
+        }
+        true
+    }
+
+    /// Reloads the stylesheet, drops every button and renders the last snapshot
+    /// from scratch.
+    async fn rebuild(&mut self, filter: Arc<Mutex<screen::DisplayFilter>>) {
+        widget::load_stylesheet(self.state.settings().stylesheet());
+
+        for (_, button) in std::mem::take(&mut self.buttons) {
+            button.stop_width_animation();
+            self.container.remove(button.get_widget());
+        }
+        for (_, launcher) in std::mem::take(&mut self.pinned_buttons) {
+            self.container.remove(launcher.get_widget());
+        }
+        for header in std::mem::take(&mut self.workspace_headers) {
+            self.container.remove(&header);
+        }
+
+        if let Some(snapshot) = self.previous_snapshot.clone() {
+            self.handle_window_update(snapshot, filter).await;
+        }
+    }
+
     async fn update_output_and_resize(&mut self) -> bool {
         let new_output = self.get_current_output_name();
 
@@ -296,6 +623,10 @@ impl ModuleInstance {
     }
 
     fn get_current_output_name(&self) -> Option<String> {
+        if let Some(output) = self.state.settings().force_output() {
+            return Some(output.to_string());
+        }
+
         let gdk_window = self.container.window()?;
         let display = gdk_window.display();
         let monitor = display.monitor_at_window(&gdk_window)?;
@@ -319,6 +650,10 @@ impl ModuleInstance {
             return screen::DisplayFilter::ShowAll;
         }
 
+        if let Some(output) = self.state.settings().force_output() {
+            return screen::DisplayFilter::Only(output.to_string());
+        }
+
         let compositor = self.state.compositor().clone();
         let available_outputs = match gio::spawn_blocking(move || compositor.query_outputs()).await {
             Ok(Ok(outputs)) => outputs,
@@ -370,6 +705,7 @@ impl ModuleInstance {
 
             let process_map = ProcessWindowMap::build(windows.iter());
             let mut matched = false;
+            let mut matched_app_ids = BTreeSet::new();
 
             loop {
                 if let Some(window) = process_map.lookup(process_id) {
@@ -379,6 +715,7 @@ impl ModuleInstance {
                                 "marking window as urgent via PID match");
                             button.mark_urgent();
                             matched = true;
+                            matched_app_ids.extend(window.app_id.as_deref());
                         }
                     }
                 }
@@ -399,20 +736,45 @@ impl ModuleInstance {
             }
 
             if matched {
+                if self.state.settings().notifications_mark_all_app_windows() {
+                    for window in windows.iter().filter(|w| !w.is_focused) {
+                        if window.app_id.as_deref().is_some_and(|id| matched_app_ids.contains(id)) {
+                            if let Some(button) = self.buttons.get(&window.id) {
+                                button.mark_urgent();
+                            }
+                        }
+                    }
+                }
                 return;
             }
         }
 
         tracing::trace!("no PID match found for notification");
 
+        if self.match_desktop_entry(windows, &notification) {
+            return;
+        }
+
+        if self.state.settings().notifications_match_app_name() && self.match_app_name(windows, &notification) {
+            return;
+        }
+
+        if self.state.settings().notifications_match_title_contains() {
+            self.match_window_titles(windows, &notification);
+        }
+    }
+
+    /// Marks windows whose app ID matches the notification's desktop entry.
+    /// Returns whether any window was marked.
+    fn match_desktop_entry(&self, windows: &WindowSnapshot, notification: &NotificationData) -> bool {
         if !self.state.settings().notifications_use_desktop_entry() {
             tracing::trace!("desktop entry matching disabled");
-            return;
+            return false;
         }
 
         let Some(desktop_entry) = &notification.get_notification().hints.desktop_entry else {
             tracing::trace!("no desktop entry in notification");
-            return;
+            return false;
         };
 
         let fuzzy_enabled = self.state.settings().notifications_use_fuzzy_matching();
@@ -454,9 +816,71 @@ impl ModuleInstance {
             }
         }
 
-        if !exact_match {
-            for window_id in fuzzy_matches {
-                if let Some(button) = self.buttons.get(&window_id) {
+        if exact_match {
+            return true;
+        }
+
+        let mut fuzzy_match = false;
+        for window_id in fuzzy_matches {
+            if let Some(button) = self.buttons.get(&window_id) {
+                button.mark_urgent();
+                fuzzy_match = true;
+            }
+        }
+        fuzzy_match
+    }
+
+    /// Marks unfocused windows whose app ID equals the notification's app name
+    /// (after `map_app_ids`), case-insensitively. For daemons and apps that
+    /// don't send a desktop entry. Returns whether any window was marked.
+    fn match_app_name(&self, windows: &WindowSnapshot, notification: &NotificationData) -> bool {
+        let Some(app_name) = notification.get_notification().app_name.as_deref().filter(|name| !name.is_empty()) else {
+            tracing::trace!("no app name in notification");
+            return false;
+        };
+
+        let mapped_name = self.state.settings()
+            .notifications_app_map(app_name)
+            .unwrap_or(app_name)
+            .to_lowercase();
+
+        let mut matched = false;
+        for window in windows.iter().filter(|w| !w.is_focused) {
+            if window.app_id.as_deref().is_some_and(|id| id.to_lowercase() == mapped_name) {
+                if let Some(button) = self.buttons.get(&window.id) {
+                    tracing::trace!(app_name, ?window, "app name match for notification");
+                    button.mark_urgent();
+                    matched = true;
+                }
+            }
+        }
+        matched
+    }
+
+    /// Marks unfocused windows whose title contains the notification summary
+    /// or body, e.g. a chat window named after the channel a message arrived
+    /// in, whichever of the two the app puts the channel in.
+    fn match_window_titles(&self, windows: &WindowSnapshot, notification: &NotificationData) {
+        let content = notification.get_notification();
+        let needles = [Some(content.summary.as_str()), content.body.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(|text| text.trim().to_lowercase())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>();
+        if needles.is_empty() {
+            return;
+        }
+
+        for window in windows.iter().filter(|w| !w.is_focused) {
+            let Some(title) = window.title.as_deref() else {
+                continue;
+            };
+
+            let title_lower = title.to_lowercase();
+            if needles.iter().any(|needle| title_lower.contains(needle)) {
+                if let Some(button) = self.buttons.get(&window.id) {
+                    tracing::trace!(title, ?window, "window title match for notification");
                     button.mark_urgent();
                 }
             }
@@ -466,30 +890,129 @@ impl ModuleInstance {
     #[tracing::instrument(level = "DEBUG", skip(self))]
     async fn handle_window_update(
         &mut self,
-        snapshot: WindowSnapshot,
+        mut snapshot: WindowSnapshot,
         filter: Arc<Mutex<screen::DisplayFilter>>,
     ) {
         self.update_output_and_resize().await;
+        if let Some(default_app_id) = self.state.settings().default_app_id() {
+            for window in snapshot.iter_mut() {
+                window.apply_default_app_id(default_app_id);
+            }
+        }
+        self.apply_persisted_order(&mut snapshot);
 
         let mut removed_windows = self.buttons.keys().copied().collect::<BTreeSet<_>>();
         let config = self.state.settings();
+        let app_counts = if config.show_app_count_badge() {
+            snapshot.iter().filter_map(|w| w.app_id.as_deref()).counts()
+        } else {
+            HashMap::new()
+        };
         let mut new_button_added = false;
+        let mut new_buttons = BTreeSet::new();
+        let mut ordered_widgets: Vec<gtk::Widget> = Vec::new();
+        let mut expanded_window = None;
+        let mut workspace_starts: Vec<(String, gtk::Widget)> = Vec::new();
+        let mut last_workspace = None;
+        // Only windows missing from the last snapshot are new; a button can also
+        // be created for a window that was hidden (another workspace with
+        // `only_current_workspace`) or after a rebuild. The first snapshot holds
+        // every window that was already open.
+        let flash_duration = config.new_window_flash_ms().map(std::time::Duration::from_millis);
+        let known_windows: Option<BTreeSet<u64>> = self.previous_snapshot.as_ref()
+            .map(|previous| previous.iter().map(|w| w.id).collect());
+
+        if let Some(focused) = snapshot.iter().find(|w| w.is_focused) {
+            self.focus_recency.retain(|&id| id != focused.id);
+            self.focus_recency.insert(0, focused.id);
+        }
+        self.focus_recency.retain(|id| snapshot.iter().any(|w| w.id == *id));
 
-        for window in snapshot.iter().filter(|w| {
+        let mut shown_windows = snapshot.iter().filter(|w| {
             if !filter.lock().expect("filter lock").should_display(w.get_output().unwrap_or_default()) {
                 return false;
             }
-            if let Some(_app_id) = &w.app_id {
-                if config.should_ignore(w.app_id.as_deref(), w.title.as_deref(), w.workspace_id) {
-                   return false;
-                }
+            if config.should_ignore(w.reported_app_id(), w.title.as_deref(), w.workspace_id, w.get_output()) {
+                return false;
+            }
+            if !config.show_minimized() && w.is_minimized(config.minimize_workspace()) {
+                return false;
             }
             true
-        }) {
+        }).collect::<Vec<_>>();
+
+        // niri doesn't report which window a dialog belongs to, so treat
+        // floating windows of a process that has a tiled window shown as its
+        // children.
+        if config.hide_child_windows() {
+            let parent_pids = shown_windows.iter()
+                .filter(|w| !w.is_floating())
+                .filter_map(|w| w.pid)
+                .collect::<BTreeSet<_>>();
+            shown_windows.retain(|w| !(w.is_floating() && w.pid.is_some_and(|pid| parent_pids.contains(&pid))));
+        }
+
+        // niri doesn't say which columns are tabbed, so every column holding
+        // several shown windows becomes one button for its most recently
+        // focused window.
+        let mut tab_groups: HashMap<u64, Vec<u64>> = HashMap::new();
+        if config.collapse_tabbed_columns() {
+            let recency = |id: u64| self.focus_recency.iter().position(|&known| known == id).unwrap_or(usize::MAX);
+            let mut columns: HashMap<(u64, usize), Vec<&WindowInfo>> = HashMap::new();
+            for window in &shown_windows {
+                if let (Some(workspace_id), Some((column, _))) = (window.workspace_id, window.layout.pos_in_scrolling_layout) {
+                    columns.entry((workspace_id, column)).or_default().push(window);
+                }
+            }
+
+            let mut collapsed = BTreeSet::new();
+            for mut tabs in columns.into_values().filter(|tabs| tabs.len() > 1) {
+                tabs.sort_by_key(|w| w.layout.pos_in_scrolling_layout);
+                let Some(shown) = tabs.iter().min_by_key(|w| recency(w.id)).map(|w| w.id) else {
+                    continue;
+                };
+                collapsed.extend(tabs.iter().map(|w| w.id).filter(|&id| id != shown));
+                tab_groups.insert(shown, tabs.iter().map(|w| w.id).collect());
+            }
+            shown_windows.retain(|w| !collapsed.contains(&w.id));
+        }
+
+        // Windows of pinned apps come first, grouped in pinned order.
+        if !config.pinned_apps().is_empty() {
+            shown_windows.sort_by_key(|w| config.pinned_index(w.app_id.as_deref()).unwrap_or(usize::MAX));
+        }
+
+        // Past `max_buttons`, the least recently focused windows move into
+        // the overflow menu; the rest keep their order.
+        let mut overflow_windows = Vec::new();
+        if let Some(max_buttons) = config.max_buttons().filter(|&max| shown_windows.len() > max) {
+            let recency = |id: u64| self.focus_recency.iter().position(|&known| known == id).unwrap_or(usize::MAX);
+            let kept = shown_windows.iter()
+                .map(|w| w.id)
+                .sorted_by_key(|&id| recency(id))
+                .take(max_buttons)
+                .collect::<BTreeSet<_>>();
+            (shown_windows, overflow_windows) = shown_windows.into_iter().partition(|w| kept.contains(&w.id));
+        }
+
+        let pinned_launchers = config.pinned_apps().iter()
+            .enumerate()
+            .filter(|(_, pinned)| {
+                !shown_windows.iter().chain(&overflow_windows)
+                    .any(|w| w.app_id.as_deref().is_some_and(|id| pinned.matches(id)))
+            })
+            .map(|(index, pinned)| {
+                let windows_before = shown_windows.iter()
+                    .filter(|w| config.pinned_index(w.app_id.as_deref()).is_some_and(|i| i < index))
+                    .count();
+                (windows_before, pinned)
+            })
+            .collect::<Vec<_>>();
+
+        for window in shown_windows {
             let button_count = (self.buttons.len() + 1) as i32;
             let output = self.current_output.as_deref();
-            let min_width = self.state.settings().min_button_width(output);
-            let max_width = self.state.settings().max_button_width(output);
+            let (min_width, max_width) = self.state.settings().button_width_limits(window.app_id.as_deref(), output);
             let total_limit = self.state.settings().max_taskbar_width_for_output(output);
             
             let initial_width = if max_width * button_count > total_limit {
@@ -500,16 +1023,37 @@ impl ModuleInstance {
 
             let button = self.buttons.entry(window.id).or_insert_with(|| {
                 new_button_added = true;
-                let btn = WindowButton::create(&self.state, window);
-                btn.get_widget().set_size_request(initial_width, -1);
+                new_buttons.insert(window.id);
+                let btn = WindowButton::create(&self.state, window, output);
+                btn.set_width(initial_width);
                 self.container.add(btn.get_widget());
+                btn.get_widget().show_all();
+                let is_new = known_windows.as_ref().is_some_and(|known| !known.contains(&window.id));
+                if let Some(duration) = flash_duration.filter(|_| is_new) {
+                    btn.flash_new_window(duration);
+                }
                 btn
             });
 
+            button.set_tab_group(tab_groups.remove(&window.id).unwrap_or_default());
             button.update_focus(window.is_focused);
+            button.update_active(window.is_active());
+            button.update_compositor_urgent(window.is_urgent && !window.is_focused);
             button.update_title(window.title.as_deref());
+            if config.rich_tooltip() {
+                button.update_rich_tooltip(window);
+            }
+            button.update_minimized(window.is_minimized(config.minimize_workspace()));
+            button.update_floating(window.is_floating());
+            button.update_other_workspace(config.dim_other_workspaces() && !window.is_on_active_workspace());
+            button.update_output(window.get_output());
+            button.update_workspace(window.get_workspace_idx());
+            button.update_workspace_name(window.get_workspace_name());
+            if let Some(app_id) = window.app_id.as_deref() {
+                button.set_app_count(app_counts.get(app_id).copied().unwrap_or(1));
+            }
             
-            if window.is_focused {
+            if (window.is_focused || window.is_active()) && self.should_autoscroll() {
                 let button_widget = button.get_widget().clone();
                 let scrolled = self.scrolled_window.clone();
                 gtk::glib::idle_add_local_once(move || {
@@ -531,38 +1075,168 @@ impl ModuleInstance {
                 });
             }
 
+            if window.is_focused || (window.is_active() && expanded_window.is_none()) {
+                expanded_window = Some(window.id);
+            }
+
             removed_windows.remove(&window.id);
-            self.container.reorder_child(button.get_widget(), -1);
+            ordered_widgets.push(button.get_widget().clone().upcast());
+
+            // Pinned apps, order weights, persisted order and MRU sorting can
+            // interleave workspaces, so every change of workspace gets a header.
+            if config.show_workspace_headers() {
+                if window.workspace_id.is_some() && window.workspace_id != last_workspace {
+                    let text = window.get_workspace_name()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| window.get_workspace_idx().to_string());
+                    workspace_starts.push((text, button.get_widget().clone().upcast()));
+                }
+                last_workspace = window.workspace_id;
+            }
         }
 
         for window_id in removed_windows {
             if let Some(button) = self.buttons.remove(&window_id) {
-                self.container.remove(button.get_widget());
+                let widget = button.get_widget().clone();
+                if config.animate_buttons() {
+                    // Keep the button in place, unclickable, until it has shrunk.
+                    widget.set_sensitive(false);
+                    let container = self.container.clone();
+                    let dying = widget.clone();
+                    button.animate_width(widget.allocated_width(), 1, move || container.remove(&dying));
+                } else {
+                    button.stop_width_animation();
+                    self.container.remove(&widget);
+                }
+            }
+        }
+
+        let mut stale_launchers = self.pinned_buttons.keys().cloned().collect::<BTreeSet<_>>();
+        for (launched_before, (windows_before, pinned)) in pinned_launchers.into_iter().enumerate() {
+            stale_launchers.remove(&pinned.app_id);
+            let launcher = self.pinned_buttons.entry(pinned.app_id.clone()).or_insert_with(|| {
+                let launcher = widget::PinnedButton::create(&self.state, pinned);
+                self.container.add(launcher.get_widget());
+                launcher.get_widget().show_all();
+                launcher
+            });
+            ordered_widgets.insert(windows_before + launched_before, launcher.get_widget().clone().upcast());
+        }
+        for app_id in stale_launchers {
+            if let Some(launcher) = self.pinned_buttons.remove(&app_id) {
+                self.container.remove(launcher.get_widget());
+            }
+        }
+
+        // Headers go in last so they don't shift the launcher positions above.
+        for stale in self.workspace_headers.drain(workspace_starts.len().min(self.workspace_headers.len())..) {
+            self.container.remove(&stale);
+        }
+        for (index, (text, first_button)) in workspace_starts.into_iter().enumerate() {
+            if index == self.workspace_headers.len() {
+                let header = gtk::Label::new(None);
+                header.style_context().add_class("workspace-header");
+                widget::add_module_styles(&header);
+                self.container.add(&header);
+                header.show();
+                self.workspace_headers.push(header);
+            }
+            let header = &self.workspace_headers[index];
+            header.set_text(&text);
+            if let Some(position) = ordered_widgets.iter().position(|widget| *widget == first_button) {
+                ordered_widgets.insert(position, header.clone().upcast());
+            }
+        }
+
+        // Stands in for the buttons while there is nothing else to show.
+        let empty_text = config.empty_text().filter(|_| self.buttons.is_empty() && self.pinned_buttons.is_empty());
+        match (empty_text, &self.empty_placeholder) {
+            (Some(text), None) => {
+                let placeholder = gtk::Label::new(Some(text));
+                placeholder.style_context().add_class("empty-placeholder");
+                widget::add_module_styles(&placeholder);
+                self.container.add(&placeholder);
+                placeholder.show();
+                self.empty_placeholder = Some(placeholder);
+            }
+            (None, Some(placeholder)) => {
+                self.container.remove(placeholder);
+                self.empty_placeholder = None;
+            }
+            _ => {}
+        }
+        if let Some(placeholder) = &self.empty_placeholder {
+            ordered_widgets.push(placeholder.clone().upcast());
+        }
+
+        if !overflow_windows.is_empty() && self.overflow.is_none() {
+            let overflow = widget::OverflowButton::create(&self.state);
+            self.container.add(overflow.get_widget());
+            self.overflow = Some(overflow);
+        }
+        if let Some(overflow) = &self.overflow {
+            overflow.set_hidden(config, &overflow_windows);
+            ordered_widgets.push(overflow.get_widget().clone().upcast());
+        }
+
+        // Only touch the child order when it actually changed; reordering
+        // every button on each update forces a full relayout.
+        if self.container.children() != ordered_widgets {
+            for (index, widget) in ordered_widgets.iter().enumerate() {
+                self.container.reorder_child(widget, index as i32);
             }
         }
 
         if !self.buttons.is_empty() {
-            let button_count = self.buttons.len() as i32;
             let output = self.current_output.as_deref();
-            let min_width = self.state.settings().min_button_width(output);
-            let max_width = self.state.settings().max_button_width(output);
             let total_limit = self.state.settings().max_taskbar_width_for_output(output);
-            
-            let final_width = if max_width * button_count > total_limit {
-                (total_limit / button_count).max(min_width).max(1)
-            } else {
-                max_width
-            }.max(1);
+            let expand_focused = self.state.settings().expand_focused_title();
+            let icon_only_width = self.state.settings().icon_size() + 16;
+            let is_collapsed = |window_id: &u64| expand_focused && expanded_window != Some(*window_id);
+
+            let limits = self.buttons.iter()
+                .map(|(window_id, button)| {
+                    if is_collapsed(window_id) {
+                        (icon_only_width, icon_only_width)
+                    } else {
+                        self.state.settings().button_width_limits(button.app_id(), output)
+                    }
+                })
+                .collect::<Vec<_>>();
 
-            for button in self.buttons.values() {
-                button.get_widget().set_size_request(final_width, -1);
+            let widths = distribute_widths(&limits, total_limit);
+            let compact = self.state.settings().icon_only_below_width().is_some_and(|threshold| {
+                self.buttons.keys().zip(&widths).any(|(window_id, &width)| !is_collapsed(window_id) && width < threshold)
+            });
+
+            let animate = self.state.settings().animate_buttons();
+            for ((window_id, button), final_width) in self.buttons.iter().zip(widths) {
+                if animate && new_buttons.contains(window_id) {
+                    button.animate_width(1, final_width, || {});
+                } else {
+                    button.set_width(final_width);
+                }
+                button.set_compact(compact || is_collapsed(window_id));
                 button.resize_for_width(final_width);
             }
         }
 
-        self.container.show_all();
+        if config.overview_behavior() == settings::OverviewBehavior::Dim {
+            let style_ctx = self.container.style_context();
+            if snapshot.first().is_some_and(|w| w.in_overview()) {
+                style_ctx.add_class("overview");
+            } else {
+                style_ctx.remove_class("overview");
+            }
+        }
+
+        self.container.show();
+
+        if self.index_overlay_visible {
+            self.refresh_index_overlay();
+        }
 
-        if new_button_added {
+        if new_button_added && self.should_autoscroll() {
             let scrolled = self.scrolled_window.clone();
             gtk::glib::timeout_add_local_once(std::time::Duration::from_millis(50), move || {
                 let hadj = scrolled.hadjustment();
@@ -570,8 +1244,107 @@ impl ModuleInstance {
             });
         }
 
+        self.publish_focus_state(&snapshot);
+
         self.previous_snapshot = Some(snapshot);
     }
+
+    /// Autoscroll is skipped for a while after the user scrolls the strip by
+    /// hand, so it doesn't fight them.
+    fn should_autoscroll(&self) -> bool {
+        let config = self.state.settings();
+        config.autoscroll_to_focused()
+            && self.last_manual_scroll.get().map_or(true, |at| {
+                at.elapsed() >= std::time::Duration::from_millis(config.autoscroll_grace_ms())
+            })
+    }
+
+    /// Learns the app order from the buttons as currently laid out (which
+    /// includes any drag-and-drop reordering), then sorts the new snapshot by
+    /// it. Workspaces keep their order; apps are only reordered within each.
+    fn apply_persisted_order(&mut self, snapshot: &mut WindowSnapshot) {
+        let config = self.state.settings();
+        if !config.persist_order() || config.order_mode() != settings::OrderMode::Position {
+            return;
+        }
+
+        // The buttons on screen belong to the previous output until this
+        // snapshot is shown, so a freshly loaded order doesn't learn from them.
+        let output = self.current_output.as_deref();
+        let order = match &mut self.persisted_order {
+            Some(order) if order.output() == output => {
+                let children = self.container.children();
+                order.remember(children.iter().filter_map(|child| {
+                    self.buttons.values()
+                        .find(|button| button.get_widget() == child)
+                        .and_then(|button| button.reported_app_id())
+                }));
+                order
+            }
+            slot => slot.insert(PersistedOrder::load(output)),
+        };
+
+        let mut workspaces: Vec<Option<u64>> = Vec::new();
+        for window in snapshot.iter() {
+            if !workspaces.contains(&window.workspace_id) {
+                workspaces.push(window.workspace_id);
+            }
+        }
+        snapshot.sort_by_key(|w| {
+            let workspace = workspaces.iter().position(|id| *id == w.workspace_id);
+            (workspace, order.rank(w.reported_app_id()))
+        });
+    }
+
+    fn publish_focus_state(&self, snapshot: &WindowSnapshot) {
+        let Some(path) = self.state.settings().focus_state_file() else {
+            return;
+        };
+        FocusState::from_window(snapshot.iter().find(|w| w.is_focused)).publish(path);
+    }
+}
+
+/// Splits `total_width` between buttons with the given `(min, max)` width
+/// limits. Buttons whose limits pin them away from an even share (e.g. apps
+/// with a fixed width) are settled first; the rest share what remains.
+fn distribute_widths(limits: &[(i32, i32)], total_width: i32) -> Vec<i32> {
+    let mut widths: Vec<Option<i32>> = vec![None; limits.len()];
+    let mut remaining = total_width;
+
+    loop {
+        let open = (0..limits.len()).filter(|&i| widths[i].is_none()).collect::<Vec<_>>();
+        if open.is_empty() {
+            break;
+        }
+
+        let share = remaining / open.len() as i32;
+        let capped = open.iter()
+            .filter(|&&i| limits[i].1 <= share)
+            .map(|&i| (i, limits[i].1))
+            .collect::<Vec<_>>();
+        let pinned = if capped.is_empty() {
+            open.iter()
+                .filter(|&&i| limits[i].0 >= share)
+                .map(|&i| (i, limits[i].0))
+                .collect::<Vec<_>>()
+        } else {
+            capped
+        };
+
+        if pinned.is_empty() {
+            for i in open {
+                widths[i] = Some(share);
+            }
+            break;
+        }
+
+        for (i, width) in pinned {
+            widths[i] = Some(width);
+            remaining -= width;
+        }
+    }
+
+    widths.into_iter().map(|w| w.unwrap_or(1).max(1)).collect()
 }
 
 struct ProcessWindowMap<'a>(HashMap<i64, &'a WindowInfo>);
@@ -588,4 +1361,34 @@ impl<'a> ProcessWindowMap<'a> {
     fn lookup(&self, pid: i64) -> Option<&'a WindowInfo> {
         self.0.get(&pid).copied()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::distribute_widths;
+
+    #[test]
+    fn widths_stop_at_max_when_everything_fits() {
+        assert_eq!(distribute_widths(&[(100, 200), (100, 200)], 1000), vec![200, 200]);
+    }
+
+    #[test]
+    fn widths_keep_minimums_when_space_runs_out() {
+        assert_eq!(distribute_widths(&[(150, 300), (150, 300), (150, 300)], 300), vec![150, 150, 150]);
+    }
+
+    #[test]
+    fn capped_buttons_leave_room_for_the_rest() {
+        assert_eq!(distribute_widths(&[(50, 100), (100, 400), (100, 400)], 600), vec![100, 250, 250]);
+    }
+
+    #[test]
+    fn large_minimums_are_settled_first() {
+        assert_eq!(distribute_widths(&[(300, 400), (100, 400), (100, 400)], 600), vec![300, 150, 150]);
+    }
+
+    #[test]
+    fn no_buttons_no_widths() {
+        assert!(distribute_widths(&[], 600).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use std::{fs, io, path::PathBuf};
+
+/// Remembers the left-to-right order of apps in the bar across restarts.
+///
+/// niri window ids are not stable across sessions, so the order is keyed by
+/// app ID: each app gets the rank of its first appearance, and windows of the
+/// same app keep their compositor order relative to each other. Every output
+/// has its own bar, so each keeps its own file.
+#[derive(Debug)]
+pub struct PersistedOrder {
+    output: Option<String>,
+    path: Option<PathBuf>,
+    apps: Vec<String>,
+}
+
+impl PersistedOrder {
+    pub fn load(output: Option<&str>) -> Self {
+        let path = state_file_path(output);
+        let apps = path
+            .as_ref()
+            .and_then(|p| match fs::read_to_string(p) {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    tracing::warn!(%e, ?p, "failed to read persisted order");
+                    None
+                }
+            })
+            .and_then(|content| match serde_json::from_str(&content) {
+                Ok(apps) => Some(apps),
+                Err(e) => {
+                    tracing::warn!(%e, "ignoring malformed persisted order");
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self { output: output.map(str::to_string), path, apps }
+    }
+
+    pub fn output(&self) -> Option<&str> {
+        self.output.as_deref()
+    }
+
+    /// Sort key for a window; apps that were never seen sort last.
+    pub fn rank(&self, app_id: Option<&str>) -> usize {
+        app_id
+            .and_then(|id| self.apps.iter().position(|known| known == id))
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Records the order the apps currently appear in and saves it if
+    /// anything changed. Apps that aren't open right now keep their place.
+    pub fn remember<'a>(&mut self, current: impl IntoIterator<Item = &'a str>) {
+        let mut visible: Vec<String> = Vec::new();
+        for app_id in current {
+            if !visible.iter().any(|known| known == app_id) {
+                visible.push(app_id.to_string());
+            }
+        }
+
+        if visible.is_empty() {
+            return;
+        }
+
+        let apps = merge_order(&self.apps, &visible);
+        if apps != self.apps {
+            self.apps = apps;
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| Ok(serde_json::to_string(&self.apps)?))
+            .and_then(|content| fs::write(path, content));
+
+        if let Err(e) = result {
+            tracing::warn!(%e, ?path, "failed to save persisted order");
+        }
+    }
+}
+
+/// Merges the `visible` apps, in their on-screen order, into the `saved`
+/// order. Visible apps that were saved swap into each other's slots, so apps
+/// missing from the screen stay between the neighbours they had; apps seen
+/// for the first time go right after the visible app before them.
+fn merge_order(saved: &[String], visible: &[String]) -> Vec<String> {
+    let mut merged = saved.to_vec();
+    let mut known_visible = visible.iter().filter(|app| saved.contains(app));
+    for slot in merged.iter_mut().filter(|app| visible.contains(app)) {
+        if let Some(app) = known_visible.next() {
+            slot.clone_from(app);
+        }
+    }
+
+    for (index, app) in visible.iter().enumerate() {
+        if merged.contains(app) {
+            continue;
+        }
+        let position = match index.checked_sub(1) {
+            Some(previous) => merged.iter().position(|known| *known == visible[previous]).map_or(merged.len(), |i| i + 1),
+            None => 0,
+        };
+        merged.insert(position, app.clone());
+    }
+
+    merged
+}
+
+fn state_file_path(output: Option<&str>) -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+
+    let file = match output {
+        Some(output) => format!("order-{output}.json"),
+        None => "order.json".to_string(),
+    };
+    Some(state_home.join("niri_window_buttons").join(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_order;
+
+    fn apps(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn absent_apps_keep_their_place() {
+        assert_eq!(merge_order(&apps(&["a", "b", "c"]), &apps(&["c"])), apps(&["a", "b", "c"]));
+    }
+
+    #[test]
+    fn visible_apps_reorder_among_themselves() {
+        assert_eq!(merge_order(&apps(&["a", "b", "c", "d"]), &apps(&["d", "b"])), apps(&["a", "d", "c", "b"]));
+    }
+
+    #[test]
+    fn new_apps_follow_their_left_neighbour() {
+        assert_eq!(merge_order(&apps(&["a", "b"]), &apps(&["a", "x", "b"])), apps(&["a", "x", "b"]));
+        assert_eq!(merge_order(&apps(&["a", "b"]), &apps(&["x", "b"])), apps(&["x", "a", "b"]));
+    }
+
+    #[test]
+    fn first_order_is_the_visible_one() {
+        assert_eq!(merge_order(&[], &apps(&["b", "a"])), apps(&["b", "a"]));
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::{Path, PathBuf}};
 use itertools::Itertools;
 use regex::Regex;
 use serde::{Deserialize, Deserializer};
@@ -13,6 +13,14 @@ pub struct OutputDimensions {
     pub max_taskbar_width: Option<i32>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppWidths {
+    #[serde(default)]
+    pub min_button_width: Option<i32>,
+    #[serde(default)]
+    pub max_button_width: Option<i32>,
+}
+
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct Settings {
     #[serde(default)]
@@ -25,6 +33,12 @@ pub struct Settings {
     only_current_workspace: bool,
     #[serde(default)]
     show_window_titles: bool,
+    #[serde(default)]
+    show_app_labels: bool,
+    #[serde(default)]
+    show_app_count_badge: bool,
+    #[serde(default)]
+    app_name_map: HashMap<String, String>,
     #[serde(default = "default_min_width")]
     min_button_width: i32,
     #[serde(default = "default_max_width")]
@@ -33,12 +47,22 @@ pub struct Settings {
     icon_size: i32,
     #[serde(default = "default_spacing")]
     icon_spacing: i32,
+    #[serde(default = "default_fallback_icon")]
+    fallback_icon: String,
     #[serde(default = "default_max_taskbar")]
     max_taskbar_width: i32,
     #[serde(default)]
     max_taskbar_width_per_output: HashMap<String, i32>,
     #[serde(default)]
+    min_button_width_per_output: HashMap<String, i32>,
+    #[serde(default)]
+    max_button_width_per_output: HashMap<String, i32>,
+    #[serde(default)]
     dimensions_per_output: HashMap<String, OutputDimensions>,
+    #[serde(default)]
+    app_widths: HashMap<String, AppWidths>,
+    #[serde(default)]
+    icon_only_below_width: Option<i32>,
     #[serde(default = "default_scroll_arrow_left")]
     scroll_arrow_left: String,
     #[serde(default = "default_scroll_arrow_right")]
@@ -46,9 +70,121 @@ pub struct Settings {
     #[serde(default)]
     click_actions: ClickActions,
     #[serde(default)]
+    focus_mode: FocusMode,
+    #[serde(default = "default_true")]
+    enable_drag_reorder: bool,
+    #[serde(default)]
+    reposition_mode: RepositionMode,
+    #[serde(default)]
+    persist_order: bool,
+    #[serde(default = "default_double_click_ms")]
+    double_click_ms: u64,
+    #[serde(default)]
+    urgent_timeout_ms: Option<u64>,
+    #[serde(default)]
+    focus_pulse: bool,
+    #[serde(default)]
     ignore_rules: Vec<IgnoreRule>,
     #[serde(default = "default_context_menu")]
     context_menu: Vec<ContextMenuItem>,
+    #[serde(default = "default_minimize_workspace")]
+    minimize_workspace: String,
+    #[serde(default)]
+    show_minimized: bool,
+    #[serde(default)]
+    focus_state_file: Option<PathBuf>,
+    #[serde(default)]
+    stylesheet: Option<PathBuf>,
+    #[serde(default)]
+    floating_position: FloatingPosition,
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(default = "default_true", alias = "autoscroll_focused")]
+    autoscroll_to_focused: bool,
+    #[serde(default = "default_autoscroll_grace_ms")]
+    autoscroll_grace_ms: u64,
+    #[serde(default)]
+    rich_tooltip: bool,
+    #[serde(default)]
+    empty_area_actions: EmptyAreaActions,
+    #[serde(default)]
+    order_mode: OrderMode,
+    #[serde(default)]
+    force_output: Option<String>,
+    #[serde(default)]
+    icon_position: IconPosition,
+    #[serde(default)]
+    expand_focused_title: bool,
+    #[serde(default)]
+    confirm_close: bool,
+    #[serde(default)]
+    dim_other_workspaces: bool,
+    #[serde(default)]
+    control_socket: bool,
+    #[serde(default)]
+    invert_scroll_arrows: bool,
+    #[serde(default)]
+    long_press_ms: Option<u64>,
+    #[serde(default = "default_action_cooldown_ms")]
+    action_cooldown_ms: u64,
+    #[serde(default)]
+    show_close_button: bool,
+    #[serde(default)]
+    max_buttons: Option<usize>,
+    #[serde(default)]
+    urgent_color: Option<String>,
+    #[serde(default)]
+    focused_color: Option<String>,
+    #[serde(default)]
+    background_color: Option<String>,
+    #[serde(default)]
+    clear_urgent_on: ClearUrgentOn,
+    #[serde(default)]
+    scroll_indicator: ScrollIndicator,
+    #[serde(default)]
+    pinned_apps: Vec<PinnedApp>,
+    #[serde(default)]
+    overview_behavior: OverviewBehavior,
+    #[serde(default)]
+    animate_buttons: bool,
+    #[serde(default)]
+    middle_click_requires_modifier: Option<ClickModifier>,
+    #[serde(default)]
+    title_max_chars: Option<i32>,
+    #[serde(default = "default_true")]
+    tooltips: bool,
+    #[serde(default)]
+    scroll_empty_switches_workspace: bool,
+    #[serde(default)]
+    default_app_id: Option<String>,
+    #[serde(default)]
+    new_window_flash_ms: Option<u64>,
+    #[serde(default)]
+    menu_focuses_first: bool,
+    #[serde(default)]
+    hide_child_windows: bool,
+    #[serde(default)]
+    show_workspace_headers: bool,
+    #[serde(default)]
+    visible_workspaces: Option<Vec<String>>,
+    #[serde(default)]
+    icon_title_separator: Option<String>,
+    #[serde(default = "default_true")]
+    hover_cursor: bool,
+    #[serde(default)]
+    title_update_interval_ms: Option<u64>,
+    #[serde(default)]
+    collapse_tabbed_columns: bool,
+    #[serde(default)]
+    min_window_age_ms: Option<u64>,
+    #[serde(default)]
+    auto_color_buttons: bool,
+    #[serde(default)]
+    dbus_control: bool,
+    #[serde(default)]
+    empty_text: Option<String>,
+    #[serde(default)]
+    auto_icon_size: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -61,6 +197,12 @@ pub struct NotificationConfig {
     use_desktop_entry: bool,
     #[serde(default)]
     use_fuzzy_matching: bool,
+    #[serde(default)]
+    mark_all_app_windows: bool,
+    #[serde(default)]
+    match_title_contains: bool,
+    #[serde(default)]
+    match_app_name: bool,
 }
 
 impl Default for NotificationConfig {
@@ -70,6 +212,9 @@ impl Default for NotificationConfig {
             map_app_ids: HashMap::new(),
             use_desktop_entry: true,
             use_fuzzy_matching: false,
+            mark_all_app_windows: false,
+            match_title_contains: false,
+            match_app_name: false,
         }
     }
 }
@@ -81,21 +226,98 @@ pub struct AppRule {
     #[serde(default)]
     class: Option<String>,
     #[serde(default)]
-    click_actions: Option<ClickActions>,
+    click_actions: Option<PartialClickActions>,
+    #[serde(default)]
+    show_title: Option<bool>,
+    #[serde(default)]
+    badge: Option<String>,
+    #[serde(default)]
+    order_weight: Option<i32>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ClickActions {
     #[serde(default = "default_left_unfocused")]
-    pub left_click_unfocused: WindowAction,
+    pub left_click_unfocused: ActionList,
     #[serde(default = "default_left_focused")]
-    pub left_click_focused: WindowAction,
+    pub left_click_focused: ActionList,
     #[serde(default = "default_double_click")]
-    pub double_click: WindowAction,
+    pub double_click: ActionList,
     #[serde(default = "default_right_click")]
-    pub right_click: WindowAction,
+    pub right_click: ActionList,
     #[serde(default = "default_middle_click")]
-    pub middle_click: WindowAction,
+    pub middle_click: ActionList,
+}
+
+/// Per-app overrides; buttons left out keep the global `click_actions`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PartialClickActions {
+    #[serde(default)]
+    left_click_unfocused: Option<ActionList>,
+    #[serde(default)]
+    left_click_focused: Option<ActionList>,
+    #[serde(default)]
+    double_click: Option<ActionList>,
+    #[serde(default)]
+    right_click: Option<ActionList>,
+    #[serde(default)]
+    middle_click: Option<ActionList>,
+}
+
+/// A single action, or several run in order, e.g.
+/// `["focus-window", "center-window"]`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(from = "ActionListConfig")]
+pub struct ActionList(Vec<WindowAction>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ActionListConfig {
+    One(WindowAction),
+    Many(Vec<WindowAction>),
+}
+
+impl From<ActionListConfig> for ActionList {
+    fn from(config: ActionListConfig) -> Self {
+        match config {
+            ActionListConfig::One(action) => Self(vec![action]),
+            ActionListConfig::Many(actions) => Self(actions),
+        }
+    }
+}
+
+impl From<WindowAction> for ActionList {
+    fn from(action: WindowAction) -> Self {
+        Self(vec![action])
+    }
+}
+
+impl ActionList {
+    pub fn actions(&self) -> &[WindowAction] {
+        &self.0
+    }
+
+    /// Whether running the list would do nothing.
+    pub fn is_none(&self) -> bool {
+        self.0.iter().all(|action| *action == WindowAction::None)
+    }
+
+    /// Whether the list is just `menu`, which opens the context menu.
+    pub fn is_menu(&self) -> bool {
+        self.0 == [WindowAction::Menu]
+    }
+}
+
+impl PartialClickActions {
+    fn merged_over(&self, base: &ClickActions) -> ClickActions {
+        ClickActions {
+            left_click_unfocused: self.left_click_unfocused.clone().unwrap_or_else(|| base.left_click_unfocused.clone()),
+            left_click_focused: self.left_click_focused.clone().unwrap_or_else(|| base.left_click_focused.clone()),
+            double_click: self.double_click.clone().unwrap_or_else(|| base.double_click.clone()),
+            right_click: self.right_click.clone().unwrap_or_else(|| base.right_click.clone()),
+            middle_click: self.middle_click.clone().unwrap_or_else(|| base.middle_click.clone()),
+        }
+    }
 }
 
 impl Default for ClickActions {
@@ -110,9 +332,10 @@ impl Default for ClickActions {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub enum WindowAction {
+    #[default]
     None,
     FocusWindow,
     CloseWindow,
@@ -120,8 +343,12 @@ pub enum WindowAction {
     MaximizeWindowToEdges,
     CenterColumn,
     CenterWindow,
+    /// Focuses the window, then centers it.
+    FocusAndCenter,
     CenterVisibleColumns,
     ExpandColumnToAvailableWidth,
+    /// niri's `fullscreen-window` toggles, so no state lookup is needed.
+    #[serde(alias = "toggle-fullscreen")]
     FullscreenWindow,
     ToggleWindowedFullscreen,
     ToggleWindowFloating,
@@ -134,15 +361,116 @@ pub enum WindowAction {
     MoveWindowToWorkspaceUp,
     MoveWindowToMonitorLeft,
     MoveWindowToMonitorRight,
+    /// Output name as listed by `niri msg outputs`.
+    MoveWindowToMonitor(String),
     ToggleColumnTabbedDisplay,
     FocusWorkspacePrevious,
+    MinimizeWindow,
+    RestoreWindow,
+    /// Restores a minimized window and minimizes any other.
+    ToggleMinimize,
+    /// Taskbar-style click: minimizes the focused window, restores a
+    /// minimized one and focuses any other.
+    FocusOrToggle,
+    CloseAppWindows,
+    /// Closes every window on the clicked window's workspace.
+    CloseWorkspaceWindows,
+    /// Shell command run with the window id, app ID and title as `$1`..`$3`;
+    /// `{id}`, `{app_id}` and `{title}` stand for `"$1"`, `"$2"` and `"$3"`.
+    RunCommand(String),
     Menu,
 }
 
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FocusMode {
+    #[default]
+    Switch,
+    Summon,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepositionMode {
+    #[default]
+    Move,
+    Swap,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IconPosition {
+    #[default]
+    Left,
+    Right,
+    Top,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OrderMode {
+    #[default]
+    Position,
+    Mru,
+    Title,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClickModifier {
+    Shift,
+    Ctrl,
+    Alt,
+    Super,
+}
+
+/// What the bar does while niri's overview is open.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverviewBehavior {
+    /// Keep updating and highlighting as usual.
+    #[default]
+    Highlight,
+    /// Stop updating until the overview closes.
+    Freeze,
+    /// Keep updating, with the `.overview` class on the bar.
+    Dim,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScrollIndicator {
+    #[default]
+    Arrows,
+    Scrollbar,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClearUrgentOn {
+    #[default]
+    Focus,
+    Click,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FloatingPosition {
+    #[default]
+    Inline,
+    Start,
+    End,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct IgnoreRule {
-    #[serde(default)]
-    pub app_id: Option<String>,
+    /// `Some(None)` comes from an explicit `null` and matches windows without
+    /// an app ID.
+    #[serde(default, deserialize_with = "parse_present")]
+    pub app_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "parse_optional_regex")]
+    pub app_id_regex: Option<Regex>,
     #[serde(default)]
     pub title: Option<String>,
     #[serde(default, deserialize_with = "parse_optional_regex")]
@@ -151,12 +479,66 @@ pub struct IgnoreRule {
     pub title_contains: Option<String>,
     #[serde(default)]
     pub workspace: Option<u64>,
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+/// Actions for clicks on the part of the bar not covered by a button. Only
+/// `none`, `menu` and `run-command` apply, since there is no window.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EmptyAreaActions {
+    #[serde(default)]
+    pub left_click: WindowAction,
+    #[serde(default)]
+    pub middle_click: WindowAction,
+    #[serde(default)]
+    pub right_click: WindowAction,
+    #[serde(default)]
+    pub menu: Vec<ContextMenuItem>,
+}
+
+/// An app kept in the bar even without windows. Written either as a plain
+/// app ID or desktop file name, or as an object with a launch command.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "PinnedAppConfig")]
+pub struct PinnedApp {
+    pub app_id: String,
+    pub desktop_id: String,
+    pub command: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PinnedAppConfig {
+    Id(String),
+    Full {
+        app_id: String,
+        #[serde(default)]
+        command: Option<String>,
+    },
+}
+
+impl From<PinnedAppConfig> for PinnedApp {
+    fn from(config: PinnedAppConfig) -> Self {
+        let (entry, command) = match config {
+            PinnedAppConfig::Id(entry) => (entry, None),
+            PinnedAppConfig::Full { app_id, command } => (app_id, command),
+        };
+        let app_id = entry.strip_suffix(".desktop").unwrap_or(&entry).to_string();
+        Self { desktop_id: format!("{app_id}.desktop"), app_id, command }
+    }
+}
+
+impl PinnedApp {
+    pub fn matches(&self, app_id: &str) -> bool {
+        self.app_id.eq_ignore_ascii_case(app_id)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ContextMenuItem {
     pub label: String,
-    pub action: WindowAction,
+    pub action: ActionList,
 }
 
 fn parse_regex<'de, D>(deserializer: D) -> Result<Regex, D::Error>
@@ -175,46 +557,126 @@ where
     pattern.map(|p| Regex::new(&p).map_err(serde::de::Error::custom)).transpose()
 }
 
+/// Wraps any present value, including `null`, in `Some`, so that a field set
+/// to `null` can be told apart from a missing one.
+fn parse_present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
 fn default_true() -> bool { true }
 fn default_min_width() -> i32 { 150 }
 fn default_max_width() -> i32 { 235 }
 fn default_icon_size() -> i32 { 24 }
 fn default_spacing() -> i32 { 6 }
 fn default_max_taskbar() -> i32 { 1200 }
+fn default_fallback_icon() -> String { "application-x-executable".to_string() }
+fn default_autoscroll_grace_ms() -> u64 { 1500 }
 fn default_scroll_arrow_left() -> String { "◀".to_string() }
 fn default_scroll_arrow_right() -> String { "▶".to_string() }
+fn default_double_click_ms() -> u64 { 300 }
+fn default_action_cooldown_ms() -> u64 { 50 }
+fn default_minimize_workspace() -> String { "minimized".to_string() }
 
-fn default_left_unfocused() -> WindowAction { WindowAction::FocusWindow }
-fn default_left_focused() -> WindowAction { WindowAction::MaximizeColumn }
-fn default_double_click() -> WindowAction { WindowAction::MaximizeWindowToEdges }
-fn default_right_click() -> WindowAction { WindowAction::Menu }
-fn default_middle_click() -> WindowAction { WindowAction::CloseWindow }
+fn default_left_unfocused() -> ActionList { WindowAction::FocusWindow.into() }
+fn default_left_focused() -> ActionList { WindowAction::MaximizeColumn.into() }
+fn default_double_click() -> ActionList { WindowAction::MaximizeWindowToEdges.into() }
+fn default_right_click() -> ActionList { WindowAction::Menu.into() }
+fn default_middle_click() -> ActionList { WindowAction::CloseWindow.into() }
 
 fn default_context_menu() -> Vec<ContextMenuItem> {
     vec![
         ContextMenuItem {
             label: "  Maximize Column".to_string(),
-            action: WindowAction::MaximizeColumn,
+            action: WindowAction::MaximizeColumn.into(),
         },
         ContextMenuItem {
             label: "  Maximize to Edges".to_string(),
-            action: WindowAction::MaximizeWindowToEdges,
+            action: WindowAction::MaximizeWindowToEdges.into(),
         },
         ContextMenuItem {
             label: "󰉩  Toggle Floating".to_string(),
-            action: WindowAction::ToggleWindowFloating,
+            action: WindowAction::ToggleWindowFloating.into(),
         },
         ContextMenuItem {
             label: "  Close Window".to_string(),
-            action: WindowAction::CloseWindow,
+            action: WindowAction::CloseWindow.into(),
         },
     ]
 }
 
+/// Finds the entry for `app_id`: an exact key, otherwise the most specific
+/// glob key that matches (most literal characters, then longest).
+fn lookup_app<'a, T>(apps: &'a HashMap<String, T>, app_id: &str) -> Option<&'a T> {
+    apps.get(app_id).or_else(|| {
+        apps.iter()
+            .filter(|(key, _)| key.contains(['*', '?']) && glob_matches(key, app_id))
+            .max_by_key(|(key, _)| (key.chars().filter(|c| !matches!(c, '*' | '?')).count(), key.len(), std::cmp::Reverse(*key)))
+            .map(|(_, value)| value)
+    })
+}
+
+/// `order_weight` rules per app, pulled out of the app rules so the window
+/// stream thread doesn't need a copy of the whole `Settings`.
+#[derive(Debug, Clone)]
+pub struct OrderWeights {
+    apps: HashMap<String, Vec<(Regex, i32)>>,
+}
+
+impl OrderWeights {
+    /// Sort weight from the first rule matching `title` that sets one.
+    pub fn weight(&self, app_id: Option<&str>, title: Option<&str>) -> Option<i32> {
+        lookup_app(&self.apps, app_id?)?
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(title.unwrap_or_default()))
+            .map(|&(_, weight)| weight)
+    }
+}
+
+/// Matches `text` against a shell-style glob where `*` matches any run of
+/// characters and `?` matches exactly one.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 impl Settings {
+    /// Rules for an app ID. An exact key wins; otherwise the glob key (`*`, `?`)
+    /// with the most literal characters that matches is used.
+    fn app_rules(&self, app_id: &str) -> Option<&Vec<AppRule>> {
+        lookup_app(&self.apps, app_id)
+    }
+
     pub fn get_app_classes(&self, app_id: &str) -> Vec<&str> {
-        self.apps
-            .get(app_id)
+        self.app_rules(app_id)
             .map(|rules| {
                 rules
                     .iter()
@@ -229,7 +691,7 @@ impl Settings {
         app_id: &str,
         title: &'a str,
     ) -> Box<dyn Iterator<Item = &'a str> + 'a> {
-        match self.apps.get(app_id) {
+        match self.app_rules(app_id) {
             Some(rules) => Box::new(
                 rules
                     .iter()
@@ -242,11 +704,11 @@ impl Settings {
 
     pub fn get_click_actions(&self, app_id: Option<&str>, title: Option<&str>) -> ClickActions {
         if let (Some(id), Some(t)) = (app_id, title) {
-            if let Some(rules) = self.apps.get(id) {
+            if let Some(rules) = self.app_rules(id) {
                 for rule in rules {
                     if rule.pattern.is_match(t) {
                         if let Some(ref actions) = rule.click_actions {
-                            return actions.clone();
+                            return actions.merged_over(&self.click_actions);
                         }
                     }
                 }
@@ -255,9 +717,58 @@ impl Settings {
         self.click_actions.clone()
     }
 
-    pub fn should_ignore(&self, app_id: Option<&str>, title: Option<&str>, workspace_id: Option<u64>) -> bool {
+    /// Whether a button shows a label at all: the first matching rule with
+    /// `show_title` decides, otherwise `show_window_titles`/`show_app_labels`.
+    pub fn show_title(&self, app_id: Option<&str>, title: Option<&str>) -> bool {
+        let rule_override = app_id
+            .and_then(|id| self.app_rules(id))
+            .and_then(|rules| {
+                rules.iter()
+                    .filter(|rule| rule.pattern.is_match(title.unwrap_or_default()))
+                    .find_map(|rule| rule.show_title)
+            });
+        rule_override.unwrap_or(self.show_window_titles || self.show_app_labels)
+    }
+
+    /// The `order_weight` rules, or `None` when no rule sets one.
+    pub fn order_weights(&self) -> Option<OrderWeights> {
+        if !self.apps.values().flatten().any(|rule| rule.order_weight.is_some()) {
+            return None;
+        }
+
+        // Every app key is kept, even without weights, so the same key wins
+        // the glob lookup as for the other rule fields.
+        let apps = self.apps.iter()
+            .map(|(key, rules)| {
+                let weights = rules.iter()
+                    .filter_map(|rule| Some((rule.pattern.clone(), rule.order_weight?)))
+                    .collect();
+                (key.clone(), weights)
+            })
+            .collect();
+        Some(OrderWeights { apps })
+    }
+
+    /// Text of the first badge among the rules matching `title`.
+    pub fn rule_badge(&self, app_id: &str, title: &str) -> Option<&str> {
+        self.app_rules(app_id)?
+            .iter()
+            .filter(|rule| rule.pattern.is_match(title))
+            .find_map(|rule| rule.badge.as_deref())
+    }
+
+    pub fn should_ignore(
+        &self,
+        app_id: Option<&str>,
+        title: Option<&str>,
+        workspace_id: Option<u64>,
+        output: Option<&str>,
+    ) -> bool {
         for rule in &self.ignore_rules {
-            let app_match = rule.app_id.as_ref().map_or(true, |id| app_id == Some(id.as_str()));
+            let app_match = rule.app_id.as_ref().map_or(true, |id| app_id == id.as_deref());
+            let app_regex_match = rule.app_id_regex.as_ref().map_or(true, |regex| {
+                app_id.map_or(false, |id| regex.is_match(id))
+            });
             let title_match = rule.title.as_ref().map_or(true, |t| title == Some(t.as_str()));
             let title_contains_match = rule.title_contains.as_ref().map_or(true, |contains| {
                 title.map_or(false, |t| t.contains(contains))
@@ -266,8 +777,9 @@ impl Settings {
                 title.map_or(false, |t| regex.is_match(t))
             });
             let workspace_match = rule.workspace.map_or(true, |ws| workspace_id == Some(ws));
+            let output_match = rule.output.as_ref().map_or(true, |o| output == Some(o.as_str()));
 
-            if app_match && title_match && title_contains_match && title_regex_match && workspace_match {
+            if app_match && app_regex_match && title_match && title_contains_match && title_regex_match && workspace_match && output_match {
                 return true;
             }
         }
@@ -290,6 +802,18 @@ impl Settings {
         self.notifications.use_fuzzy_matching
     }
 
+    pub fn notifications_mark_all_app_windows(&self) -> bool {
+        self.notifications.mark_all_app_windows
+    }
+
+    pub fn notifications_match_title_contains(&self) -> bool {
+        self.notifications.match_title_contains
+    }
+
+    pub fn notifications_match_app_name(&self) -> bool {
+        self.notifications.match_app_name
+    }
+
     pub fn show_all_outputs(&self) -> bool {
         self.show_all_outputs
     }
@@ -298,24 +822,55 @@ impl Settings {
         self.only_current_workspace
     }
 
-    pub fn show_window_titles(&self) -> bool {
-        self.show_window_titles
+    pub fn show_app_labels(&self) -> bool {
+        self.show_app_labels
+    }
+
+    pub fn show_app_count_badge(&self) -> bool {
+        self.show_app_count_badge
+    }
+
+    pub fn app_label<'a>(&'a self, app_id: &'a str) -> &'a str {
+        self.app_name_map.get(app_id).map(String::as_str).unwrap_or(app_id)
     }
 
     pub fn min_button_width(&self, output: Option<&str>) -> i32 {
         output
-            .and_then(|name| self.dimensions_per_output.get(name))
-            .and_then(|dims| dims.min_button_width)
+            .and_then(|name| {
+                self.dimensions_per_output.get(name)
+                    .and_then(|dims| dims.min_button_width)
+                    .or_else(|| self.min_button_width_per_output.get(name).copied())
+            })
             .unwrap_or(self.min_button_width)
     }
 
     pub fn max_button_width(&self, output: Option<&str>) -> i32 {
         output
-            .and_then(|name| self.dimensions_per_output.get(name))
-            .and_then(|dims| dims.max_button_width)
+            .and_then(|name| {
+                self.dimensions_per_output.get(name)
+                    .and_then(|dims| dims.max_button_width)
+                    .or_else(|| self.max_button_width_per_output.get(name).copied())
+            })
             .unwrap_or(self.max_button_width)
     }
 
+    /// Returns the `(min, max)` button width for a window, preferring the
+    /// app's entry in `app_widths` over the per-output and global values.
+    pub fn button_width_limits(&self, app_id: Option<&str>, output: Option<&str>) -> (i32, i32) {
+        let app_widths = app_id.and_then(|id| lookup_app(&self.app_widths, id));
+        let min_width = app_widths
+            .and_then(|widths| widths.min_button_width)
+            .unwrap_or_else(|| self.min_button_width(output));
+        let max_width = app_widths
+            .and_then(|widths| widths.max_button_width)
+            .unwrap_or_else(|| self.max_button_width(output));
+        (min_width, max_width)
+    }
+
+    pub fn icon_only_below_width(&self) -> Option<i32> {
+        self.icon_only_below_width
+    }
+
     pub fn icon_size(&self) -> i32 {
         self.icon_size
     }
@@ -324,6 +879,10 @@ impl Settings {
         self.icon_spacing
     }
 
+    pub fn fallback_icon(&self) -> &str {
+        &self.fallback_icon
+    }
+
     pub fn max_taskbar_width_for_output(&self, output: Option<&str>) -> i32 {
         output
             .and_then(|name| {
@@ -342,7 +901,324 @@ impl Settings {
         &self.scroll_arrow_right
     }
 
+    pub fn focus_mode(&self) -> FocusMode {
+        self.focus_mode
+    }
+
+    pub fn enable_drag_reorder(&self) -> bool {
+        self.enable_drag_reorder
+    }
+
+    pub fn reposition_mode(&self) -> RepositionMode {
+        self.reposition_mode
+    }
+
+    pub fn persist_order(&self) -> bool {
+        self.persist_order
+    }
+
+    pub fn double_click_ms(&self) -> u64 {
+        self.double_click_ms
+    }
+
+    pub fn urgent_timeout_ms(&self) -> Option<u64> {
+        self.urgent_timeout_ms
+    }
+
+    pub fn focus_pulse(&self) -> bool {
+        self.focus_pulse
+    }
+
     pub fn context_menu(&self) -> &[ContextMenuItem] {
         &self.context_menu
     }
-}
\ No newline at end of file
+
+    pub fn minimize_workspace(&self) -> &str {
+        &self.minimize_workspace
+    }
+
+    pub fn show_minimized(&self) -> bool {
+        self.show_minimized
+    }
+
+    pub fn focus_state_file(&self) -> Option<&Path> {
+        self.focus_state_file.as_deref()
+    }
+
+    pub fn stylesheet(&self) -> Option<&Path> {
+        self.stylesheet.as_deref()
+    }
+
+    pub fn floating_position(&self) -> FloatingPosition {
+        self.floating_position
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn autoscroll_to_focused(&self) -> bool {
+        self.autoscroll_to_focused
+    }
+
+    pub fn autoscroll_grace_ms(&self) -> u64 {
+        self.autoscroll_grace_ms
+    }
+
+    pub fn rich_tooltip(&self) -> bool {
+        self.rich_tooltip
+    }
+
+    pub fn empty_area_actions(&self) -> &EmptyAreaActions {
+        &self.empty_area_actions
+    }
+
+    pub fn order_mode(&self) -> OrderMode {
+        self.order_mode
+    }
+
+    pub fn force_output(&self) -> Option<&str> {
+        self.force_output.as_deref()
+    }
+
+    pub fn icon_position(&self) -> IconPosition {
+        self.icon_position
+    }
+
+    pub fn expand_focused_title(&self) -> bool {
+        self.expand_focused_title
+    }
+
+    pub fn confirm_close(&self) -> bool {
+        self.confirm_close
+    }
+
+    pub fn dim_other_workspaces(&self) -> bool {
+        self.dim_other_workspaces
+    }
+
+    pub fn control_socket(&self) -> bool {
+        self.control_socket
+    }
+
+    pub fn invert_scroll_arrows(&self) -> bool {
+        self.invert_scroll_arrows
+    }
+
+    pub fn long_press_ms(&self) -> Option<u64> {
+        self.long_press_ms
+    }
+
+    pub fn action_cooldown_ms(&self) -> u64 {
+        self.action_cooldown_ms
+    }
+
+    pub fn show_close_button(&self) -> bool {
+        self.show_close_button
+    }
+
+    pub fn max_buttons(&self) -> Option<usize> {
+        self.max_buttons
+    }
+
+    pub fn urgent_color(&self) -> Option<&str> {
+        self.urgent_color.as_deref()
+    }
+
+    pub fn focused_color(&self) -> Option<&str> {
+        self.focused_color.as_deref()
+    }
+
+    pub fn background_color(&self) -> Option<&str> {
+        self.background_color.as_deref()
+    }
+
+    pub fn clear_urgent_on(&self) -> ClearUrgentOn {
+        self.clear_urgent_on
+    }
+
+    pub fn scroll_indicator(&self) -> ScrollIndicator {
+        self.scroll_indicator
+    }
+
+    pub fn overview_behavior(&self) -> OverviewBehavior {
+        self.overview_behavior
+    }
+
+    pub fn animate_buttons(&self) -> bool {
+        self.animate_buttons
+    }
+
+    pub fn middle_click_requires_modifier(&self) -> Option<ClickModifier> {
+        self.middle_click_requires_modifier
+    }
+
+    pub fn title_max_chars(&self) -> Option<i32> {
+        self.title_max_chars
+    }
+
+    pub fn tooltips(&self) -> bool {
+        self.tooltips
+    }
+
+    pub fn scroll_empty_switches_workspace(&self) -> bool {
+        self.scroll_empty_switches_workspace
+    }
+
+    pub fn default_app_id(&self) -> Option<&str> {
+        self.default_app_id.as_deref()
+    }
+
+    pub fn new_window_flash_ms(&self) -> Option<u64> {
+        self.new_window_flash_ms
+    }
+
+    pub fn menu_focuses_first(&self) -> bool {
+        self.menu_focuses_first
+    }
+
+    pub fn hide_child_windows(&self) -> bool {
+        self.hide_child_windows
+    }
+
+    pub fn show_workspace_headers(&self) -> bool {
+        self.show_workspace_headers
+    }
+
+    pub fn visible_workspaces(&self) -> Option<&[String]> {
+        self.visible_workspaces.as_deref()
+    }
+
+    pub fn icon_title_separator(&self) -> Option<&str> {
+        self.icon_title_separator.as_deref()
+    }
+
+    pub fn hover_cursor(&self) -> bool {
+        self.hover_cursor
+    }
+
+    pub fn title_update_interval_ms(&self) -> Option<u64> {
+        self.title_update_interval_ms
+    }
+
+    pub fn collapse_tabbed_columns(&self) -> bool {
+        self.collapse_tabbed_columns
+    }
+
+    pub fn min_window_age_ms(&self) -> Option<u64> {
+        self.min_window_age_ms
+    }
+
+    pub fn auto_color_buttons(&self) -> bool {
+        self.auto_color_buttons
+    }
+
+    pub fn dbus_control(&self) -> bool {
+        self.dbus_control
+    }
+
+    pub fn empty_text(&self) -> Option<&str> {
+        self.empty_text.as_deref()
+    }
+
+    pub fn auto_icon_size(&self) -> bool {
+        self.auto_icon_size
+    }
+
+    pub fn pinned_apps(&self) -> &[PinnedApp] {
+        &self.pinned_apps
+    }
+
+    /// Position of the pinned app `app_id` belongs to, if any.
+    pub fn pinned_index(&self, app_id: Option<&str>) -> Option<usize> {
+        let app_id = app_id?;
+        self.pinned_apps.iter().position(|pinned| pinned.matches(app_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_matches, Settings};
+
+    fn settings(json: serde_json::Value) -> Settings {
+        serde_json::from_value(json).expect("valid settings")
+    }
+
+    fn width_settings() -> Settings {
+        settings(serde_json::json!({
+            "min_button_width": 100,
+            "max_button_width": 200,
+            "max_taskbar_width": 1000,
+            "min_button_width_per_output": { "DP-1": 110, "DP-2": 120 },
+            "max_taskbar_width_per_output": { "DP-1": 900, "DP-2": 800 },
+            "dimensions_per_output": {
+                "DP-1": { "min_button_width": 130, "max_button_width": 260, "max_taskbar_width": 700 }
+            },
+            "app_widths": {
+                "firefox": { "max_button_width": 400 },
+                "fire*": { "min_button_width": 90 }
+            }
+        }))
+    }
+
+    #[test]
+    fn button_widths_fall_back_to_globals() {
+        let settings = width_settings();
+        assert_eq!(settings.button_width_limits(None, None), (100, 200));
+        assert_eq!(settings.button_width_limits(Some("foot"), Some("HDMI-A-1")), (100, 200));
+    }
+
+    #[test]
+    fn dimensions_per_output_beat_per_output_maps() {
+        let settings = width_settings();
+        assert_eq!(settings.button_width_limits(None, Some("DP-1")), (130, 260));
+        assert_eq!(settings.button_width_limits(None, Some("DP-2")), (120, 200));
+    }
+
+    #[test]
+    fn app_widths_beat_output_widths() {
+        let settings = width_settings();
+        assert_eq!(settings.button_width_limits(Some("firefox"), Some("DP-1")), (130, 400));
+        assert_eq!(settings.button_width_limits(Some("firefox"), None), (100, 400));
+        assert_eq!(settings.button_width_limits(Some("firefox-esr"), Some("DP-1")), (90, 260));
+    }
+
+    #[test]
+    fn taskbar_width_per_output() {
+        let settings = width_settings();
+        assert_eq!(settings.max_taskbar_width_for_output(Some("DP-1")), 700);
+        assert_eq!(settings.max_taskbar_width_for_output(Some("DP-2")), 800);
+        assert_eq!(settings.max_taskbar_width_for_output(Some("HDMI-A-1")), 1000);
+        assert_eq!(settings.max_taskbar_width_for_output(None), 1000);
+    }
+
+    #[test]
+    fn glob_star_matches_any_run() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*", "firefox"));
+        assert!(glob_matches("fire*fox", "firefox"));
+        assert!(glob_matches("fire*fox", "fire-dev-fox"));
+        assert!(!glob_matches("fire*fox", "firefox-esr"));
+    }
+
+    #[test]
+    fn glob_question_mark_matches_one_char() {
+        assert!(glob_matches("DP-?", "DP-1"));
+        assert!(!glob_matches("DP-?", "DP-"));
+        assert!(!glob_matches("DP-?", "DP-10"));
+    }
+
+    #[test]
+    fn glob_is_anchored_at_both_ends() {
+        assert!(glob_matches("org.*", "org.gnome.Nautilus"));
+        assert!(!glob_matches("gnome*", "org.gnome.Nautilus"));
+        assert!(glob_matches("*Nautilus", "org.gnome.Nautilus"));
+        assert!(!glob_matches("*gnome", "org.gnome.Nautilus"));
+    }
+
+    #[test]
+    fn empty_glob_matches_only_empty_text() {
+        assert!(glob_matches("", ""));
+        assert!(!glob_matches("", "foot"));
+    }
+}
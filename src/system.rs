@@ -41,6 +41,39 @@ impl ProcessInfo {
     }
 }
 
+/// Launches the app described by the desktop file `desktop_id` (e.g.
+/// `firefox.desktop`) from the XDG data directories.
+pub fn launch_desktop_entry(desktop_id: &str) -> Result<(), glib::Error> {
+    use waybar_cffi::gtk::gio::{self, prelude::AppInfoExt};
+
+    let Some(info) = gio::DesktopAppInfo::new(desktop_id) else {
+        return Err(glib::Error::new(gio::IOErrorEnum::NotFound, &format!("no desktop file named {desktop_id}")));
+    };
+    info.launch(&[], None::<&gio::AppLaunchContext>)
+}
+
+/// Runs `command` through `sh -c` without waiting for it; a helper thread
+/// reaps the child and logs a non-zero exit. `args` become the positional
+/// parameters `$1`, `$2`, ..., so their values never reach the shell parser.
+pub fn spawn_shell_command(command: &str, args: &[&str]) -> std::io::Result<()> {
+    let mut child = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .arg("sh")
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .spawn()?;
+
+    let command = command.to_owned();
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => tracing::warn!(%status, %command, "command exited unsuccessfully"),
+        Ok(_) => {}
+        Err(e) => tracing::warn!(%e, %command, "failed to wait for command"),
+    });
+
+    Ok(())
+}
+
 #[derive(Error, Debug)]
 pub enum ProcessError {
     #[error("malformed /proc/{pid}/stat: missing fields")]
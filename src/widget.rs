@@ -1,28 +1,53 @@
-use std::{cell::RefCell, fmt::Debug, path::PathBuf, rc::Rc, time::{Duration, Instant}};
+use std::{cell::{Cell, RefCell}, collections::HashMap, fmt::Debug, path::{Path, PathBuf}, rc::Rc, time::{Duration, Instant}};
 use waybar_cffi::gtk::{
     self as gtk, CssProvider, IconLookupFlags, IconSize, IconTheme, Menu, MenuItem, Orientation, ReliefStyle,
     gdk_pixbuf::Pixbuf,
-    prelude::{BoxExt, ButtonExt, Cast, ContainerExt, CssProviderExt, DragContextExtManual, GdkPixbufExt, GtkMenuExt, GtkMenuItemExt, IconThemeExt, LabelExt, MenuShellExt, StyleContextExt, WidgetExt, WidgetExtManual},
+    gio::{self, prelude::{FileExt, FileMonitorExt}},
+    prelude::{BoxExt, ButtonExt, OverlayExt, Cast, ContainerExt, CssProviderExt, DragContextExtManual, GdkPixbufExt, GtkMenuExt, GtkMenuItemExt, IconThemeExt, LabelExt, MenuShellExt, StyleContextExt, WidgetExt, WidgetExtManual},
     DestDefaults, TargetEntry, TargetFlags,
 };
-use crate::global::SharedState;
+use crate::{compositor::WindowInfo, errors::ModuleError, global::SharedState, settings::{ClearUrgentOn, IconPosition, Settings}};
 
 pub struct WindowButton {
     app_id: Option<String>,
+    /// The app ID niri reported; `app_id` may be `default_app_id` instead.
+    reported_app_id: Option<String>,
+    root: gtk::Overlay,
     gtk_button: gtk::Button,
     layout_box: gtk::Box,
+    overlay: gtk::Overlay,
+    count_badge: Option<gtk::Label>,
+    index_label: gtk::Label,
+    rule_badge: gtk::Label,
+    close_button: Option<gtk::Button>,
     title_label: gtk::Label,
-    display_titles: bool,
+    separator_label: Option<gtk::Label>,
+    display_titles: Rc<Cell<bool>>,
+    app_label: Option<String>,
     state: SharedState,
     window_id: u64,
     title: Rc<RefCell<Option<String>>>,
+    urgent_timeout: Rc<RefCell<Option<gtk::glib::SourceId>>>,
+    output_class: Rc<RefCell<Option<String>>>,
+    workspace_class: Rc<RefCell<Option<String>>>,
+    workspace_name_class: Rc<RefCell<Option<String>>>,
+    compact: Rc<Cell<bool>>,
+    pulse_timeout: Rc<RefCell<Option<gtk::glib::SourceId>>>,
+    compositor_urgent: Rc<Cell<bool>>,
+    long_press_timeout: Rc<RefCell<Option<gtk::glib::SourceId>>>,
+    long_press_fired: Rc<Cell<bool>>,
+    title_applied_at: Rc<Cell<Option<Instant>>>,
+    pending_title: Rc<RefCell<Option<Option<String>>>>,
+    title_timeout: Rc<RefCell<Option<gtk::glib::SourceId>>>,
+    tab_group: Rc<RefCell<Vec<u64>>>,
+    width_tween: Rc<RefCell<Option<gtk::glib::SourceId>>>,
 }
 
 impl Debug for WindowButton {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("WindowButton")
             .field("app_id", &self.app_id)
-            .field("display_titles", &self.display_titles)
+            .field("display_titles", &self.display_titles.get())
             .field("window_id", &self.window_id)
             .finish()
     }
@@ -31,63 +56,305 @@ impl Debug for WindowButton {
 thread_local! {
     static BUTTON_STYLES: CssProvider = {
         let provider = CssProvider::new();
-        if let Err(e) = provider.load_from_data(include_bytes!("styles.css")) {
-            tracing::error!(%e, "failed to load CSS");
-        }
+        reload_styles(&provider, None);
         provider
     };
 
+    static STYLESHEET_MONITOR: RefCell<Option<gio::FileMonitor>> = const { RefCell::new(None) };
+
+    /// Provider for the `*_color` settings, keyed by the CSS it was built from.
+    static COLOR_STYLES: RefCell<Option<(String, CssProvider)>> = const { RefCell::new(None) };
+
+    /// Providers for `auto_color_buttons`, one per app ID.
+    static APP_COLOR_STYLES: RefCell<HashMap<String, CssProvider>> = RefCell::new(HashMap::new());
+
     static ICON_THEME_INSTANCE: IconTheme = IconTheme::default().unwrap_or_default();
 }
 
+/// Points the button styles at the user's stylesheet, or back at the embedded
+/// one, and reloads them whenever the file changes on disk.
+pub fn load_stylesheet(path: Option<&Path>) {
+    BUTTON_STYLES.with(|provider| reload_styles(provider, path));
+
+    let monitor = path.and_then(|path| {
+        let monitor = gio::File::for_path(path)
+            .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+            .inspect_err(|e| tracing::warn!(%e, ?path, "failed to watch stylesheet"))
+            .ok()?;
+
+        let path = path.to_path_buf();
+        monitor.connect_changed(move |_, _, _, event| {
+            if matches!(event, gio::FileMonitorEvent::ChangesDoneHint | gio::FileMonitorEvent::Created) {
+                tracing::info!(?path, "stylesheet changed, reloading");
+                BUTTON_STYLES.with(|provider| reload_styles(provider, Some(&path)));
+            }
+        });
+        Some(monitor)
+    });
+
+    STYLESHEET_MONITOR.with(|slot| {
+        if let Some(previous) = slot.replace(monitor) {
+            previous.cancel();
+        }
+    });
+}
+
+/// Adds the module stylesheet to a widget that isn't part of a button. Style
+/// providers don't cascade to children, so each styled widget needs its own.
+pub fn add_module_styles(widget: &impl gtk::prelude::IsA<gtk::Widget>) {
+    BUTTON_STYLES.with(|provider| {
+        widget.style_context().add_provider(provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+    });
+}
+
+/// Applies the `*_color` settings to `widget`. They sit just above the module's
+/// own styles, so rules in Waybar's style.css still override them.
+fn apply_color_styles(settings: &Settings, widget: &impl gtk::prelude::IsA<gtk::Widget>) {
+    let rules = [
+        ("button", settings.background_color()),
+        ("button.focused", settings.focused_color()),
+        ("button.urgent", settings.urgent_color()),
+    ];
+
+    let css: String = rules
+        .into_iter()
+        .filter_map(|(selector, color)| {
+            let color = color?;
+            if color.contains(['{', '}', ';']) {
+                tracing::warn!(color, "ignoring invalid color");
+                return None;
+            }
+            Some(format!("{selector} {{ background-color: {color}; }}\n"))
+        })
+        .collect();
+    if css.is_empty() {
+        return;
+    }
+
+    COLOR_STYLES.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        if slot.as_ref().map_or(true, |(cached, _)| *cached != css) {
+            let provider = CssProvider::new();
+            if let Err(e) = provider.load_from_data(css.as_bytes()) {
+                tracing::warn!(%e, "failed to load color settings");
+                return;
+            }
+            *slot = Some((css, provider));
+        }
+
+        if let Some((_, provider)) = slot.as_ref() {
+            widget.style_context().add_provider(provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION + 1);
+        }
+    });
+}
+
+/// Gives `widget` an accent derived from `app_id` for `auto_color_buttons`, so
+/// each app keeps the same color across restarts without any configuration.
+fn apply_app_color(app_id: &str, widget: &impl gtk::prelude::IsA<gtk::Widget>) {
+    APP_COLOR_STYLES.with(|providers| {
+        let mut providers = providers.borrow_mut();
+        let provider = providers.entry(app_id.to_string()).or_insert_with(|| {
+            let (r, g, b) = app_color(app_id);
+            let css = format!("button {{ border-bottom: 2px solid rgb({r}, {g}, {b}); }}");
+            let provider = CssProvider::new();
+            if let Err(e) = provider.load_from_data(css.as_bytes()) {
+                tracing::warn!(%e, app_id, "failed to load app color");
+            }
+            provider
+        });
+        widget.style_context().add_provider(provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION + 1);
+    });
+    widget.style_context().add_class("auto-color");
+}
+
+/// Stable color for `app_id`: an FNV-1a hash picks the hue, with fixed
+/// saturation and lightness so every app gets an equally readable accent.
+fn app_color(app_id: &str) -> (u8, u8, u8) {
+    let hash = app_id.bytes().fold(0x811c9dc5u32, |hash, byte| (hash ^ u32::from(byte)).wrapping_mul(0x01000193));
+    hsl_to_rgb(f64::from(hash % 360), 0.6, 0.6)
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f64| ((value + m) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+fn reload_styles(provider: &CssProvider, path: Option<&Path>) {
+    if let Some(path) = path {
+        match provider.load_from_path(&path.to_string_lossy()) {
+            Ok(()) => return,
+            Err(e) => tracing::warn!(%e, ?path, "failed to load stylesheet, using built-in styles"),
+        }
+    }
+
+    if let Err(e) = provider.load_from_data(include_bytes!("styles.css")) {
+        tracing::error!(%e, "failed to load CSS");
+    }
+}
+
 impl WindowButton {
     #[tracing::instrument(level = "TRACE", fields(app_id = &window.app_id))]
-    pub fn create(state: &SharedState, window: &niri_ipc::Window) -> Self {
+    pub fn create(state: &SharedState, window: &WindowInfo, output: Option<&str>) -> Self {
         let state_clone = state.clone();
-        let display_titles = state.settings().show_window_titles();
+        let display_titles = state.settings().show_title(window.app_id.as_deref(), window.title.as_deref());
 
         let icon_gap = state.settings().icon_spacing();
-        let layout_box = gtk::Box::new(Orientation::Horizontal, icon_gap);
+        let icon_on_top = state.settings().icon_position() == IconPosition::Top;
+        let orientation = if icon_on_top { Orientation::Vertical } else { Orientation::Horizontal };
+        let layout_box = gtk::Box::new(orientation, icon_gap);
 
         let title_label = gtk::Label::new(None);
         title_label.set_ellipsize(gtk::pango::EllipsizeMode::End);
-        title_label.set_xalign(0.0);
+        title_label.set_xalign(if icon_on_top { 0.5 } else { 0.0 });
+        title_label.set_no_show_all(true);
+
+        let separator_label = state.settings().icon_title_separator().map(|separator| {
+            let label = gtk::Label::new(Some(separator));
+            label.style_context().add_class("icon-title-separator");
+            label.set_no_show_all(true);
+            label
+        });
 
         let gtk_button = gtk::Button::new();
         gtk_button.set_always_show_image(true);
         gtk_button.set_relief(ReliefStyle::None);
-        gtk_button.add(&layout_box);
+        gtk_button.set_can_focus(true);
+        gtk_button.set_focus_on_click(false);
+        // The close button can't live inside `gtk_button`: a button swallows the
+        // clicks of anything nested in it. Both sit side by side in `root` instead.
+        let root = gtk::Overlay::new();
+        root.add(&gtk_button);
+        let overlay = gtk::Overlay::new();
+        overlay.add(&layout_box);
+        gtk_button.add(&overlay);
+
+        let count_badge = state.settings().show_app_count_badge().then(|| {
+            let badge = gtk::Label::new(None);
+            badge.style_context().add_class("app-count-badge");
+            badge.set_halign(gtk::Align::End);
+            badge.set_valign(gtk::Align::Start);
+            badge.set_no_show_all(true);
+            BUTTON_STYLES.with(|provider| {
+                badge.style_context().add_provider(provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+            });
+            overlay.add_overlay(&badge);
+            badge
+        });
 
-        let max_width = state.settings().max_button_width(None);
-        gtk_button.set_size_request(max_width, -1);
+        let index_label = gtk::Label::new(None);
+        index_label.style_context().add_class("index-overlay");
+        index_label.set_halign(gtk::Align::Start);
+        index_label.set_valign(gtk::Align::Start);
+        index_label.set_no_show_all(true);
+        BUTTON_STYLES.with(|provider| {
+            index_label.style_context().add_provider(provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+        });
+        overlay.add_overlay(&index_label);
 
-        if display_titles {
-            let icon_dim = state.settings().icon_size();
-            let max_chars = (max_width - icon_dim - icon_gap - 16) / 8;
-            title_label.set_max_width_chars(max_chars);
-        }
+        let rule_badge = gtk::Label::new(None);
+        rule_badge.style_context().add_class("rule-badge");
+        rule_badge.set_halign(gtk::Align::End);
+        rule_badge.set_valign(gtk::Align::End);
+        rule_badge.set_no_show_all(true);
+        BUTTON_STYLES.with(|provider| {
+            rule_badge.style_context().add_provider(provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+        });
+        overlay.add_overlay(&rule_badge);
+
+        let close_button = state.settings().show_close_button().then(|| {
+            let close = gtk::Button::with_label("×");
+            close.set_relief(ReliefStyle::None);
+            close.set_focus_on_click(false);
+            close.style_context().add_class("window-close-button");
+            close.set_halign(gtk::Align::End);
+            close.set_valign(gtk::Align::Start);
+            close.set_no_show_all(true);
+            BUTTON_STYLES.with(|provider| {
+                close.style_context().add_provider(provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+            });
+            root.add_overlay(&close);
+            close
+        });
+
+        let (_, max_width) = state.settings().button_width_limits(window.app_id.as_deref(), output);
+        root.set_size_request(max_width, -1);
+
+        title_label.set_max_width_chars(title_max_chars(state.settings(), max_width));
 
         BUTTON_STYLES.with(|provider| {
             gtk_button.style_context().add_provider(provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
         });
+        apply_color_styles(state.settings(), &gtk_button);
+        if let Some(app_id) = window.app_id.as_deref().filter(|_| state.settings().auto_color_buttons()) {
+            apply_app_color(app_id, &gtk_button);
+        }
 
         let app_id = window.app_id.clone();
         let icon_location = app_id.as_deref().and_then(|id| state_clone.icon_resolver().resolve(id));
+        let app_label = app_id.as_deref()
+            .filter(|_| state.settings().show_app_labels())
+            .map(|id| state.settings().app_label(id).to_string());
 
         let button = Self {
             app_id,
+            reported_app_id: window.reported_app_id().map(str::to_string),
+            root,
             gtk_button,
             layout_box,
+            overlay,
+            count_badge,
+            index_label,
+            rule_badge,
+            close_button,
             title_label,
-            display_titles,
+            separator_label,
+            display_titles: Rc::new(Cell::new(display_titles)),
+            app_label,
             state: state_clone,
             window_id: window.id,
             title: Rc::new(RefCell::new(window.title.clone())),
+            urgent_timeout: Rc::new(RefCell::new(None)),
+            output_class: Rc::new(RefCell::new(None)),
+            workspace_class: Rc::new(RefCell::new(None)),
+            workspace_name_class: Rc::new(RefCell::new(None)),
+            compact: Rc::new(Cell::new(false)),
+            pulse_timeout: Rc::new(RefCell::new(None)),
+            compositor_urgent: Rc::new(Cell::new(false)),
+            long_press_timeout: Rc::new(RefCell::new(None)),
+            long_press_fired: Rc::new(Cell::new(false)),
+            title_applied_at: Rc::new(Cell::new(None)),
+            pending_title: Rc::new(RefCell::new(None)),
+            title_timeout: Rc::new(RefCell::new(None)),
+            tab_group: Rc::new(RefCell::new(Vec::new())),
+            width_tween: Rc::new(RefCell::new(None)),
         };
 
-        button.setup_click_handlers(window.id);
-        button.setup_drag_reorder();
+        let double_click_window = Duration::from_millis(state.settings().double_click_ms());
+        button.setup_click_handlers(window.id, double_click_window);
+        button.setup_keyboard_activation(window.id);
+        if let Some(long_press_ms) = state.settings().long_press_ms() {
+            button.setup_long_press(window.id, Duration::from_millis(long_press_ms));
+        }
+        if state.settings().enable_drag_reorder() {
+            button.setup_drag_reorder();
+        }
         button.setup_icon_rendering(icon_location);
+        button.setup_close_button();
+        if state.settings().hover_cursor() {
+            button.setup_hover_cursor();
+        }
 
         button
     }
@@ -95,33 +362,174 @@ impl WindowButton {
     #[tracing::instrument(level = "TRACE")]
     pub fn update_focus(&self, is_focused: bool) {
         let style_ctx = self.gtk_button.style_context();
+        let was_focused = style_ctx.has_class("focused");
         if is_focused {
             style_ctx.add_class("focused");
-            style_ctx.remove_class("urgent");
+            if self.state.settings().clear_urgent_on() == ClearUrgentOn::Focus {
+                self.clear_urgent();
+            }
+            if !was_focused && self.state.settings().focus_pulse() {
+                self.start_pulse();
+            }
         } else {
             style_ctx.remove_class("focused");
+            self.stop_pulse();
         }
         self.gtk_button.queue_draw();
     }
 
+    #[tracing::instrument(level = "TRACE")]
+    pub fn update_active(&self, is_active: bool) {
+        let style_ctx = self.gtk_button.style_context();
+        if is_active {
+            style_ctx.add_class("active");
+        } else {
+            style_ctx.remove_class("active");
+        }
+    }
+
+    /// Toggles the `pulse` class a few times as a one-shot focus animation.
+    fn start_pulse(&self) {
+        const PULSE_TOGGLES: u32 = 6;
+
+        self.stop_pulse();
+
+        let button = self.gtk_button.clone();
+        let pending = self.pulse_timeout.clone();
+        let mut toggles = 0;
+        let source = gtk::glib::timeout_add_local(Duration::from_millis(150), move || {
+            toggles += 1;
+            let style_ctx = button.style_context();
+            if toggles >= PULSE_TOGGLES {
+                style_ctx.remove_class("pulse");
+                pending.borrow_mut().take();
+                return gtk::glib::ControlFlow::Break;
+            }
+
+            if style_ctx.has_class("pulse") {
+                style_ctx.remove_class("pulse");
+            } else {
+                style_ctx.add_class("pulse");
+            }
+            gtk::glib::ControlFlow::Continue
+        });
+        *self.pulse_timeout.borrow_mut() = Some(source);
+    }
+
+    /// Adds the `new-window` class for `duration`.
+    pub fn flash_new_window(&self, duration: Duration) {
+        let button = self.gtk_button.clone();
+        button.style_context().add_class("new-window");
+        gtk::glib::timeout_add_local_once(duration, move || {
+            button.style_context().remove_class("new-window");
+        });
+    }
+
+    /// Sets the button's width, stopping any width animation still running so
+    /// it can't overwrite the new value on its next frame.
+    pub fn set_width(&self, width: i32) {
+        self.stop_width_animation();
+        self.root.set_size_request(width, -1);
+    }
+
+    /// Eases the button's width from `from` to `to`, fading it in when growing
+    /// and out when shrinking, then runs `done`. Replaces any running animation.
+    pub fn animate_width(&self, from: i32, to: i32, done: impl FnOnce() + 'static) {
+        const DURATION_MS: f64 = 150.0;
+
+        self.stop_width_animation();
+
+        let start_time = Instant::now();
+        let widget = self.root.clone();
+        let growing = to > from;
+        let pending = self.width_tween.clone();
+        let mut done = Some(done);
+
+        widget.set_size_request(from, -1);
+        widget.set_opacity(if growing { 0.0 } else { 1.0 });
+
+        let source = gtk::glib::timeout_add_local(Duration::from_millis(16), move || {
+            let elapsed = start_time.elapsed().as_millis() as f64;
+            let progress = (elapsed / DURATION_MS).min(1.0);
+
+            let eased = crate::ease_out_cubic(progress);
+            widget.set_size_request(from + ((to - from) as f64 * eased).round() as i32, -1);
+            widget.set_opacity(if growing { eased } else { 1.0 - eased });
+
+            if progress >= 1.0 {
+                pending.borrow_mut().take();
+                if let Some(done) = done.take() {
+                    done();
+                }
+                gtk::glib::ControlFlow::Break
+            } else {
+                gtk::glib::ControlFlow::Continue
+            }
+        });
+        *self.width_tween.borrow_mut() = Some(source);
+    }
+
+    /// Cancels a running width animation, leaving the button fully opaque.
+    pub fn stop_width_animation(&self) {
+        if let Some(source) = self.width_tween.borrow_mut().take() {
+            source.remove();
+            self.root.set_opacity(1.0);
+        }
+    }
+
+    fn stop_pulse(&self) {
+        if let Some(source) = self.pulse_timeout.borrow_mut().take() {
+            source.remove();
+        }
+        self.gtk_button.style_context().remove_class("pulse");
+    }
+
+    /// With `title_update_interval_ms`, titles arriving faster than the
+    /// interval are held back and only the latest is shown once it passes.
     #[tracing::instrument(level = "TRACE")]
     pub fn update_title(&self, title: Option<&str>) {
         if let Some(t) = title {
             *self.title.borrow_mut() = Some(t.to_string());
         }
 
-        self.gtk_button.set_tooltip_text(title);
-
-        if self.display_titles {
-            if let Some(text) = title {
-                self.title_label.set_text(text);
-                self.title_label.show();
-            } else {
-                self.title_label.set_text("");
-                self.title_label.hide();
+        if let Some(interval) = self.state.settings().title_update_interval_ms().map(Duration::from_millis) {
+            let elapsed = self.title_applied_at.get().map(|at| at.elapsed());
+            if let Some(elapsed) = elapsed.filter(|&elapsed| elapsed < interval) {
+                *self.pending_title.borrow_mut() = Some(title.map(str::to_string));
+                if self.title_timeout.borrow().is_none() {
+                    let button = self.clone_for_menu();
+                    let source = gtk::glib::timeout_add_local_once(interval - elapsed, move || {
+                        button.title_timeout.borrow_mut().take();
+                        let pending = button.pending_title.borrow_mut().take();
+                        if let Some(title) = pending {
+                            button.apply_title(title.as_deref());
+                        }
+                    });
+                    *self.title_timeout.borrow_mut() = Some(source);
+                }
+                return;
             }
         }
 
+        self.pending_title.borrow_mut().take();
+        self.apply_title(title);
+    }
+
+    fn apply_title(&self, title: Option<&str>) {
+        self.title_applied_at.set(Some(Instant::now()));
+
+        // The rich tooltip is refreshed with every snapshot; a held-back title
+        // applied later must not replace it.
+        if self.state.settings().tooltips() && !self.state.settings().rich_tooltip() {
+            self.gtk_button.set_tooltip_text(title);
+        }
+
+        // `show_title` rules can match on the title, so re-check them as it changes.
+        let display_titles = self.state.settings().show_title(self.app_id.as_deref(), self.title.borrow().as_deref());
+        self.display_titles.set(display_titles);
+        self.title_label.set_text(self.app_label.as_deref().or(title).unwrap_or_default());
+        self.refresh_title_visibility();
+
         if let Some(app_id) = &self.app_id {
             if let Some(window_title) = title {
                 let config = self.state.settings();
@@ -134,20 +542,200 @@ impl WindowButton {
                 for class in config.match_app_rules(app_id, window_title) {
                     style_ctx.add_class(class);
                 }
+
+                match config.rule_badge(app_id, window_title) {
+                    Some(badge) => {
+                        self.rule_badge.set_text(badge);
+                        self.rule_badge.show();
+                    }
+                    None => self.rule_badge.hide(),
+                }
+            }
+        }
+    }
+
+    /// Replaces the title tooltip with a multi-line summary of the window.
+    pub fn update_rich_tooltip(&self, window: &WindowInfo) {
+        if !self.state.settings().tooltips() {
+            return;
+        }
+
+        let mut lines = vec![window.title.clone().unwrap_or_default()];
+        lines.push(format!("App ID: {}", window.app_id.as_deref().unwrap_or("-")));
+        lines.push(format!("PID: {}", window.pid.map_or_else(|| "-".to_string(), |pid| pid.to_string())));
+
+        let workspace = match window.get_workspace_name() {
+            Some(name) => format!("{} ({name})", window.get_workspace_idx()),
+            None => window.get_workspace_idx().to_string(),
+        };
+        lines.push(match window.get_output() {
+            Some(output) => format!("Workspace: {workspace} on {output}"),
+            None => format!("Workspace: {workspace}"),
+        });
+        lines.push(format!("Floating: {}", if window.is_floating() { "yes" } else { "no" }));
+
+        self.gtk_button.set_tooltip_text(Some(&lines.join("\n")));
+    }
+
+    #[tracing::instrument(level = "TRACE")]
+    pub fn update_minimized(&self, is_minimized: bool) {
+        let style_ctx = self.gtk_button.style_context();
+        if is_minimized {
+            style_ctx.add_class("minimized");
+        } else {
+            style_ctx.remove_class("minimized");
+        }
+    }
+
+    #[tracing::instrument(level = "TRACE")]
+    pub fn update_other_workspace(&self, is_other: bool) {
+        let style_ctx = self.gtk_button.style_context();
+        if is_other {
+            style_ctx.add_class("other-workspace");
+        } else {
+            style_ctx.remove_class("other-workspace");
+        }
+    }
+
+    #[tracing::instrument(level = "TRACE")]
+    pub fn update_floating(&self, is_floating: bool) {
+        let style_ctx = self.gtk_button.style_context();
+        if is_floating {
+            style_ctx.add_class("floating");
+        } else {
+            style_ctx.remove_class("floating");
+        }
+    }
+
+    #[tracing::instrument(level = "TRACE")]
+    pub fn update_output(&self, output: Option<&str>) {
+        self.replace_class(&self.output_class, output.map(|name| css_class_name("output", name)));
+    }
+
+    #[tracing::instrument(level = "TRACE")]
+    pub fn update_workspace(&self, workspace_idx: u8) {
+        self.replace_class(&self.workspace_class, Some(format!("workspace-{workspace_idx}")));
+    }
+
+    pub fn update_workspace_name(&self, workspace_name: Option<&str>) {
+        self.replace_class(&self.workspace_name_class, workspace_name.map(|name| css_class_name("workspace-name", name)));
+    }
+
+    /// Shows how many windows this button's app has; hidden for a single window.
+    pub fn set_app_count(&self, count: usize) {
+        let Some(badge) = &self.count_badge else {
+            return;
+        };
+
+        if count > 1 {
+            badge.set_text(&count.to_string());
+            badge.show();
+        } else {
+            badge.hide();
+        }
+    }
+
+    /// Shows `index` in the corner of the button, or hides it with `None`.
+    pub fn set_index_overlay(&self, index: Option<usize>) {
+        match index {
+            Some(index) => {
+                self.index_label.set_text(&index.to_string());
+                self.index_label.show();
             }
+            None => self.index_label.hide(),
         }
     }
 
+    /// Runs `action` on this button's window as if it had been clicked.
+    pub fn run_action(&self, action: &crate::settings::WindowAction) {
+        Self::execute_action(&self.state, self.window_id, self.reported_app_id.as_deref(), self.title.borrow().as_deref(), action);
+    }
+
+    /// Swaps the class remembered in `slot` for `class`, so a value derived
+    /// from window state never leaves a stale class behind when it changes.
+    fn replace_class(&self, slot: &RefCell<Option<String>>, class: Option<String>) {
+        let mut current = slot.borrow_mut();
+        if *current == class {
+            return;
+        }
+
+        let style_ctx = self.gtk_button.style_context();
+        if let Some(old) = current.take() {
+            style_ctx.remove_class(&old);
+        }
+        if let Some(new) = &class {
+            style_ctx.add_class(new);
+        }
+        *current = class;
+    }
+
     #[tracing::instrument(level = "TRACE")]
     pub fn mark_urgent(&self) {
         self.gtk_button.style_context().add_class("urgent");
+
+        if let Some(timeout_ms) = self.state.settings().urgent_timeout_ms() {
+            self.cancel_urgent_timeout();
+
+            let button = self.gtk_button.clone();
+            let pending = self.urgent_timeout.clone();
+            let source = gtk::glib::timeout_add_local_once(Duration::from_millis(timeout_ms), move || {
+                pending.borrow_mut().take();
+                button.style_context().remove_class("urgent");
+            });
+            *self.urgent_timeout.borrow_mut() = Some(source);
+        }
     }
 
-    pub fn get_widget(&self) -> &gtk::Button {
-        &self.gtk_button
+    /// Follows the urgency niri reports for the window. Only a change is acted
+    /// on, so an urgent mark from a notification isn't cleared by a window
+    /// that was never urgent in niri's eyes. With `clear_urgent_on = "click"`
+    /// niri dropping the urgency is ignored too.
+    pub fn update_compositor_urgent(&self, is_urgent: bool) {
+        let clear_on_focus = self.state.settings().clear_urgent_on() == ClearUrgentOn::Focus;
+        match (self.compositor_urgent.replace(is_urgent), is_urgent) {
+            (false, true) => self.mark_urgent(),
+            (true, false) if clear_on_focus => self.clear_urgent(),
+            _ => {}
+        }
+    }
+
+    fn clear_urgent(&self) {
+        self.cancel_urgent_timeout();
+        self.gtk_button.style_context().remove_class("urgent");
+    }
+
+    fn cancel_urgent_timeout(&self) {
+        if let Some(source) = self.urgent_timeout.borrow_mut().take() {
+            source.remove();
+        }
+    }
+
+    /// Windows of the column this button stands for with
+    /// `collapse_tabbed_columns`, in tab order; empty for a lone window.
+    pub fn set_tab_group(&self, window_ids: Vec<u64>) {
+        let style_ctx = self.gtk_button.style_context();
+        if window_ids.len() > 1 {
+            style_ctx.add_class("tab-group");
+        } else {
+            style_ctx.remove_class("tab-group");
+        }
+        *self.tab_group.borrow_mut() = window_ids;
     }
 
-	fn setup_click_handlers(&self, window_id: u64) {
+    pub fn app_id(&self) -> Option<&str> {
+        self.app_id.as_deref()
+    }
+
+    pub fn reported_app_id(&self) -> Option<&str> {
+        self.reported_app_id.as_deref()
+    }
+
+    /// The widget to place in the taskbar: the window button plus its close button.
+    pub fn get_widget(&self) -> &gtk::Overlay {
+        &self.root
+    }
+
+	fn setup_click_handlers(&self, window_id: u64, double_click_window: Duration) {
 		let state = self.state.clone();
 		let state_middle = self.state.clone();
 		let state_right = self.state.clone();
@@ -156,45 +744,87 @@ impl WindowButton {
 		let app_id = self.app_id.clone();
 		let app_id_middle = self.app_id.clone();
 		let app_id_right = self.app_id.clone();
+		let reported_app_id = self.reported_app_id.clone();
+		let reported_app_id_middle = self.reported_app_id.clone();
 		let title = self.title.clone();
 
 		let title_clone = title.clone();
+		let long_press_fired = self.long_press_fired.clone();
+		let tab_group = self.tab_group.clone();
 		self.gtk_button.connect_clicked(move |_| {
+		    if long_press_fired.replace(false) {
+		        return;
+		    }
 		    let is_currently_focused = button_ref.style_context().has_class("focused");
+
+		    // A collapsed column cycles through its tabs instead.
+		    if is_currently_focused && tab_group.borrow().len() > 1 {
+		        let tabs = tab_group.borrow();
+		        let next = tabs.iter()
+		            .position(|&id| id == window_id)
+		            .map_or(tabs[0], |index| tabs[(index + 1) % tabs.len()]);
+		        if let Err(e) = state.compositor().focus_window(next) {
+		            tracing::warn!(%e, id = next, "focus next tab failed");
+		        }
+		        return;
+		    }
+
 		    let actions = state.settings().get_click_actions(
 		        app_id.as_deref(),
 		        title_clone.borrow().as_deref()
 		    );
 
-		    if is_currently_focused {
-		        let mut last_click = last_click_time.borrow_mut();
-		        let now = Instant::now();
-		        let time_since_last = now.duration_since(*last_click);
-		        
-		        if time_since_last < Duration::from_millis(300) {
-		            Self::execute_action(&state, window_id, &actions.double_click);
-		            *last_click = Instant::now() - Duration::from_secs(1);
-		        } else {
-		            Self::execute_action(&state, window_id, &actions.left_click_focused);
+		    let mut last_click = last_click_time.borrow_mut();
+		    let now = Instant::now();
+		    let kind = classify_left_click(
+		        is_currently_focused,
+		        now.duration_since(*last_click),
+		        double_click_window,
+		        !actions.double_click.is_none(),
+		    );
+		    let action_list = match kind {
+		        LeftClick::Unfocused => &actions.left_click_unfocused,
+		        LeftClick::Focused => {
 		            *last_click = now;
+		            &actions.left_click_focused
 		        }
-		    } else {
-		        Self::execute_action(&state, window_id, &actions.left_click_unfocused);
-		    }
+		        LeftClick::Double => {
+		            *last_click = now - Duration::from_secs(1);
+		            &actions.double_click
+		        }
+		    };
+		    Self::execute_actions(&state, window_id, reported_app_id.as_deref(), title_clone.borrow().as_deref(), action_list.actions());
 		});
 
 		let menu_self = self.clone_for_menu();
 		let title_middle = title.clone();
 		self.gtk_button.connect_button_press_event(move |_, event| {
+		    if state_middle.settings().clear_urgent_on() == ClearUrgentOn::Click {
+		        menu_self.clear_urgent();
+		    }
 		    if event.button() == 2 {
+		        if let Some(modifier) = state_middle.settings().middle_click_requires_modifier() {
+		            use crate::settings::ClickModifier;
+		            use gtk::gdk::ModifierType;
+		            let mask = match modifier {
+		                ClickModifier::Shift => ModifierType::SHIFT_MASK,
+		                ClickModifier::Ctrl => ModifierType::CONTROL_MASK,
+		                ClickModifier::Alt => ModifierType::MOD1_MASK,
+		                ClickModifier::Super => ModifierType::SUPER_MASK | ModifierType::MOD4_MASK,
+		            };
+		            if !event.state().intersects(mask) {
+		                tracing::debug!(id = window_id, ?modifier, "middle click without required modifier");
+		                return gtk::glib::Propagation::Stop;
+		            }
+		        }
 		        let actions = state_middle.settings().get_click_actions(
 		            app_id_middle.as_deref(),
 		            title_middle.borrow().as_deref()
 		        );
-		        if actions.middle_click == crate::settings::WindowAction::Menu {
+		        if actions.middle_click.is_menu() {
 		            menu_self.display_context_menu(window_id);
 		        } else {
-		            Self::execute_action(&state_middle, window_id, &actions.middle_click);
+		            Self::execute_actions(&state_middle, window_id, reported_app_id_middle.as_deref(), title_middle.borrow().as_deref(), actions.middle_click.actions());
 		        }
 		        gtk::glib::Propagation::Stop
 		    } else if event.button() == 3 {
@@ -202,10 +832,10 @@ impl WindowButton {
 		            app_id_right.as_deref(),
 		            title_middle.borrow().as_deref()
 		        );
-		        if actions.right_click == crate::settings::WindowAction::Menu {
+		        if actions.right_click.is_menu() {
 		            menu_self.display_context_menu(window_id);
 		        } else {
-		            Self::execute_action(&state_right, window_id, &actions.right_click);
+		            Self::execute_actions(&state_right, window_id, reported_app_id_middle.as_deref(), title_middle.borrow().as_deref(), actions.right_click.actions());
 		        }
 		        gtk::glib::Propagation::Stop
 		    } else {
@@ -214,126 +844,454 @@ impl WindowButton {
 		});
 	}
 
-    fn execute_action(state: &SharedState, window_id: u64, action: &crate::settings::WindowAction) {
-        use crate::settings::WindowAction;
+    /// Opens the context menu when the button is held for `delay`, mainly for
+    /// touchscreens. Releasing early or starting a drag cancels it, and the
+    /// click that ends a long press is swallowed.
+    fn setup_long_press(&self, window_id: u64, delay: Duration) {
+        let pending = self.long_press_timeout.clone();
+        let fired = self.long_press_fired.clone();
+        let menu_self = self.clone_for_menu();
+        self.gtk_button.connect_button_press_event(move |_, event| {
+            if event.button() != 1 || event.event_type() != gtk::gdk::EventType::ButtonPress {
+                return gtk::glib::Propagation::Proceed;
+            }
+
+            fired.set(false);
+            if let Some(source) = pending.borrow_mut().take() {
+                source.remove();
+            }
+
+            let pending_inner = pending.clone();
+            let fired = fired.clone();
+            let menu_self = menu_self.clone_for_menu();
+            let source = gtk::glib::timeout_add_local_once(delay, move || {
+                pending_inner.borrow_mut().take();
+                fired.set(true);
+                menu_self.display_context_menu(window_id);
+            });
+            *pending.borrow_mut() = Some(source);
+            gtk::glib::Propagation::Proceed
+        });
+
+        let pending = self.long_press_timeout.clone();
+        self.gtk_button.connect_button_release_event(move |_, _| {
+            if let Some(source) = pending.borrow_mut().take() {
+                source.remove();
+            }
+            gtk::glib::Propagation::Proceed
+        });
+
+        let pending = self.long_press_timeout.clone();
+        self.gtk_button.connect_drag_begin(move |_, _| {
+            if let Some(source) = pending.borrow_mut().take() {
+                source.remove();
+            }
+        });
+    }
+
+    /// Shows the close button while the pointer is over the window button.
+    /// Moving onto the close button itself counts as still being inside.
+    fn setup_close_button(&self) {
+        let Some(close) = self.close_button.clone() else {
+            return;
+        };
+
+        let menu_self = self.clone_for_menu();
+        close.connect_clicked(move |_| menu_self.run_action(&crate::settings::WindowAction::CloseWindow));
+
+        let close_on_enter = close.clone();
+        self.gtk_button.connect_enter_notify_event(move |_, _| {
+            close_on_enter.show();
+            gtk::glib::Propagation::Proceed
+        });
+
+        let close_on_leave = close.clone();
+        self.gtk_button.connect_leave_notify_event(move |button, event| {
+            let (x, y) = event.position();
+            if !pointer_inside(button, x, y) {
+                close_on_leave.hide();
+            }
+            gtk::glib::Propagation::Proceed
+        });
+
+        let button = self.gtk_button.clone();
+        close.connect_leave_notify_event(move |close, event| {
+            let (x, y) = event.position();
+            let inside_button = close.translate_coordinates(&button, x as i32, y as i32)
+                .is_some_and(|(bx, by)| pointer_inside(&button, bx as f64, by as f64));
+            if !inside_button {
+                close.hide();
+            }
+            gtk::glib::Propagation::Proceed
+        });
+    }
+
+    /// Shows the `pointer` cursor over the button. It is set on the button's
+    /// own input window on enter and cleared on leave; starting a drag grabs
+    /// the pointer, which also sends a leave, so it doesn't linger then.
+    fn setup_hover_cursor(&self) {
+        self.gtk_button.connect_enter_notify_event(|button, event| {
+            if let Some(window) = event.window() {
+                let cursor = gtk::gdk::Cursor::from_name(&button.display(), "pointer");
+                window.set_cursor(cursor.as_ref());
+            }
+            gtk::glib::Propagation::Proceed
+        });
+
+        self.gtk_button.connect_leave_notify_event(|_, event| {
+            if let Some(window) = event.window() {
+                window.set_cursor(None);
+            }
+            gtk::glib::Propagation::Proceed
+        });
+    }
+
+    /// Enter and Space run the `left_click_unfocused` action, so keyboard users
+    /// get the same result regardless of the window's focus state.
+    fn setup_keyboard_activation(&self, window_id: u64) {
+        use gtk::gdk::keys::constants as key;
+
+        let state = self.state.clone();
+        let app_id = self.app_id.clone();
+        let reported_app_id = self.reported_app_id.clone();
+        let title = self.title.clone();
+        self.gtk_button.connect_key_press_event(move |_, event| {
+            let keyval = event.keyval();
+            if keyval != key::Return && keyval != key::KP_Enter && keyval != key::space {
+                return gtk::glib::Propagation::Proceed;
+            }
+
+            let actions = state.settings().get_click_actions(app_id.as_deref(), title.borrow().as_deref());
+            Self::execute_actions(&state, window_id, reported_app_id.as_deref(), title.borrow().as_deref(), actions.left_click_unfocused.actions());
+            gtk::glib::Propagation::Stop
+        });
+    }
+
+    /// Runs `actions` in order, e.g. `["focus-window", "center-window"]`,
+    /// stopping at the first one that fails.
+    fn execute_actions(
+        state: &SharedState,
+        window_id: u64,
+        app_id: Option<&str>,
+        title: Option<&str>,
+        actions: &[crate::settings::WindowAction],
+    ) {
+        for (step, action) in actions.iter().enumerate() {
+            if !Self::execute_action(state, window_id, app_id, title, action) {
+                if actions.len() > 1 {
+                    tracing::warn!(step = step + 1, ?action, id = window_id, "action sequence stopped");
+                }
+                return;
+            }
+        }
+    }
+
+    /// Returns whether the action went through; a failure is already logged.
+    /// Dry runs, throttled repeats and closes awaiting confirmation count as
+    /// success. `app_id` is the one niri reported, never `default_app_id`, so
+    /// app-wide actions don't target a placeholder no niri window has.
+    fn execute_action(
+        state: &SharedState,
+        window_id: u64,
+        app_id: Option<&str>,
+        title: Option<&str>,
+        action: &crate::settings::WindowAction,
+    ) -> bool {
+        use crate::settings::{FocusMode, WindowAction};
+        if state.settings().dry_run() && !matches!(action, WindowAction::None | WindowAction::Menu) {
+            tracing::info!(?action, id = window_id, app_id, "dry run: skipping action");
+            return true;
+        }
+        if !matches!(action, WindowAction::None | WindowAction::Menu) && state.should_throttle(window_id, action) {
+            tracing::debug!(?action, id = window_id, "ignoring repeated action");
+            return true;
+        }
         match action {
             WindowAction::None => {}
             WindowAction::FocusWindow => {
-                if let Err(e) = state.compositor().focus_window(window_id) {
+                let result = match state.settings().focus_mode() {
+                    FocusMode::Switch => state.compositor().focus_window(window_id),
+                    FocusMode::Summon => state.compositor().summon_window(window_id),
+                };
+                if let Err(e) = result {
                     tracing::warn!(%e, id = window_id, "focus failed");
+                    return false;
                 }
             }
             WindowAction::CloseWindow => {
-                if let Err(e) = state.compositor().close_window(window_id) {
-                    tracing::warn!(%e, id = window_id, "close failed");
-                }
+                let state_clone = state.clone();
+                let prompt = format!("Close {}?", title.unwrap_or("window"));
+                Self::confirm_close(state, &prompt, move || {
+                    if let Err(e) = state_clone.compositor().close_window(window_id) {
+                        tracing::warn!(%e, id = window_id, "close failed");
+                    }
+                });
             }
             WindowAction::MaximizeColumn => {
                 if let Err(e) = state.compositor().maximize_window_column(window_id) {
                     tracing::warn!(%e, id = window_id, "maximize column failed");
+                    return false;
                 }
             }
             WindowAction::MaximizeWindowToEdges => {
                 if let Err(e) = state.compositor().maximize_window_to_edges(window_id) {
                     tracing::warn!(%e, id = window_id, "maximize to edges failed");
+                    return false;
                 }
             }
             WindowAction::CenterColumn => {
                 if let Err(e) = state.compositor().center_column(window_id) {
                     tracing::warn!(%e, id = window_id, "center column failed");
+                    return false;
                 }
             }
             WindowAction::CenterWindow => {
                 if let Err(e) = state.compositor().center_window(window_id) {
                     tracing::warn!(%e, id = window_id, "center window failed");
+                    return false;
+                }
+            }
+            WindowAction::FocusAndCenter => {
+                let result = state.compositor().focus_window(window_id)
+                    .and_then(|()| state.compositor().center_window(window_id));
+                if let Err(e) = result {
+                    tracing::warn!(%e, id = window_id, "focus and center failed");
+                    return false;
                 }
             }
             WindowAction::CenterVisibleColumns => {
                 if let Err(e) = state.compositor().center_visible_columns(window_id) {
                     tracing::warn!(%e, id = window_id, "center visible columns failed");
+                    return false;
                 }
             }
             WindowAction::ExpandColumnToAvailableWidth => {
                 if let Err(e) = state.compositor().expand_column_to_available_width(window_id) {
                     tracing::warn!(%e, id = window_id, "expand column failed");
+                    return false;
                 }
             }
             WindowAction::FullscreenWindow => {
                 if let Err(e) = state.compositor().fullscreen_window(window_id) {
                     tracing::warn!(%e, id = window_id, "fullscreen failed");
+                    return false;
                 }
             }
             WindowAction::ToggleWindowedFullscreen => {
                 if let Err(e) = state.compositor().toggle_windowed_fullscreen(window_id) {
                     tracing::warn!(%e, id = window_id, "toggle windowed fullscreen failed");
+                    return false;
                 }
             }
             WindowAction::ToggleWindowFloating => {
                 if let Err(e) = state.compositor().toggle_floating(window_id) {
                     tracing::warn!(%e, id = window_id, "toggle floating failed");
+                    return false;
                 }
             }
             WindowAction::ConsumeWindowIntoColumn => {
                 if let Err(e) = state.compositor().consume_window_into_column(window_id) {
                     tracing::warn!(%e, id = window_id, "consume window into column failed");
+                    return false;
                 }
             }
             WindowAction::ExpelWindowFromColumn => {
                 if let Err(e) = state.compositor().expel_window_from_column(window_id) {
                     tracing::warn!(%e, id = window_id, "expel window from column failed");
+                    return false;
                 }
             }
             WindowAction::ResetWindowHeight => {
                 if let Err(e) = state.compositor().reset_window_height(window_id) {
                     tracing::warn!(%e, id = window_id, "reset window height failed");
+                    return false;
                 }
             }
             WindowAction::SwitchPresetColumnWidth => {
                 if let Err(e) = state.compositor().switch_preset_column_width(window_id) {
                     tracing::warn!(%e, id = window_id, "switch preset column width failed");
+                    return false;
                 }
             }
             WindowAction::SwitchPresetWindowHeight => {
                 if let Err(e) = state.compositor().switch_preset_window_height(window_id) {
                     tracing::warn!(%e, id = window_id, "switch preset window height failed");
+                    return false;
                 }
             }
             WindowAction::MoveWindowToWorkspaceDown => {
                 if let Err(e) = state.compositor().move_window_to_workspace_down(window_id) {
                     tracing::warn!(%e, id = window_id, "move window to workspace down failed");
+                    return false;
                 }
             }
             WindowAction::MoveWindowToWorkspaceUp => {
                 if let Err(e) = state.compositor().move_window_to_workspace_up(window_id) {
                     tracing::warn!(%e, id = window_id, "move window to workspace up failed");
+                    return false;
                 }
             }
             WindowAction::MoveWindowToMonitorLeft => {
                 if let Err(e) = state.compositor().move_window_to_monitor_left(window_id) {
                     tracing::warn!(%e, id = window_id, "move window to monitor left failed");
+                    return false;
                 }
             }
             WindowAction::MoveWindowToMonitorRight => {
                 if let Err(e) = state.compositor().move_window_to_monitor_right(window_id) {
                     tracing::warn!(%e, id = window_id, "move window to monitor right failed");
+                    return false;
+                }
+            }
+            WindowAction::MoveWindowToMonitor(output) => {
+                if let Err(e) = state.compositor().move_window_to_named_monitor(window_id, output) {
+                    tracing::warn!(%e, id = window_id, %output, "move window to monitor failed");
+                    return false;
                 }
             }
             WindowAction::ToggleColumnTabbedDisplay => {
                 if let Err(e) = state.compositor().toggle_column_tabbed_display(window_id) {
                     tracing::warn!(%e, id = window_id, "toggle column tabbed display failed");
+                    return false;
                 }
             }
             WindowAction::FocusWorkspacePrevious => {
                 if let Err(e) = state.compositor().focus_workspace_previous(window_id) {
                     tracing::warn!(%e, id = window_id, "focus workspace previous failed");
+                    return false;
+                }
+            }
+            WindowAction::MinimizeWindow => {
+                if let Err(e) = minimize(state, window_id) {
+                    tracing::warn!(%e, id = window_id, "minimize window failed");
+                    return false;
+                }
+            }
+            WindowAction::RestoreWindow => {
+                if let Err(e) = restore(state, window_id) {
+                    tracing::warn!(%e, id = window_id, "restore window failed");
+                    return false;
+                }
+            }
+            WindowAction::ToggleMinimize => {
+                let result = state.compositor().is_window_minimized(window_id).and_then(|minimized| {
+                    if minimized { restore(state, window_id) } else { minimize(state, window_id) }
+                });
+                if let Err(e) = result {
+                    tracing::warn!(%e, id = window_id, "toggle minimize failed");
+                    return false;
+                }
+            }
+            WindowAction::FocusOrToggle => {
+                let compositor = state.compositor();
+                let result = compositor.is_window_minimized(window_id).and_then(|minimized| {
+                    if minimized {
+                        return restore(state, window_id);
+                    }
+                    if compositor.is_window_focused(window_id)? {
+                        return minimize(state, window_id);
+                    }
+                    match state.settings().focus_mode() {
+                        FocusMode::Switch => compositor.focus_window(window_id),
+                        FocusMode::Summon => compositor.summon_window(window_id),
+                    }
+                });
+                if let Err(e) = result {
+                    tracing::warn!(%e, id = window_id, "focus or toggle failed");
+                    return false;
+                }
+            }
+            WindowAction::CloseAppWindows => {
+                let Some(app_id) = app_id else {
+                    tracing::warn!(id = window_id, "cannot close app windows for window without app ID");
+                    return false;
+                };
+                let state_clone = state.clone();
+                let app_id = app_id.to_string();
+                let prompt = format!("Close all {} windows?", state.settings().app_label(&app_id));
+                Self::confirm_close(state, &prompt, move || {
+                    match state_clone.compositor().close_app_windows(&app_id) {
+                        Ok(closed) => tracing::info!(%app_id, closed, "closed app windows"),
+                        Err(e) => tracing::warn!(%e, %app_id, "close app windows failed"),
+                    }
+                });
+            }
+            WindowAction::CloseWorkspaceWindows => {
+                let window_ids = match state.compositor().workspace_window_ids(window_id) {
+                    Ok(ids) if !ids.is_empty() => ids,
+                    Ok(_) => {
+                        tracing::warn!(id = window_id, "window is not on a workspace");
+                        return false;
+                    }
+                    Err(e) => {
+                        tracing::warn!(%e, id = window_id, "failed to list workspace windows");
+                        return false;
+                    }
+                };
+                let state_clone = state.clone();
+                let prompt = format!("Close {} windows on this workspace?", window_ids.len());
+                Self::confirm_close(state, &prompt, move || {
+                    match state_clone.compositor().close_windows(&window_ids) {
+                        Ok(closed) => tracing::info!(id = window_id, closed, "closed workspace windows"),
+                        Err(e) => tracing::warn!(%e, id = window_id, "close workspace windows failed"),
+                    }
+                });
+            }
+            WindowAction::RunCommand(template) => {
+                // Placeholders turn into quoted references to the positional
+                // parameters rather than the values themselves, so a title
+                // can't inject shell syntax. They already carry their quotes,
+                // so templates must leave them unquoted, as the README says;
+                // inside single quotes they wouldn't expand at all.
+                let command = template
+                    .replace("{id}", "\"$1\"")
+                    .replace("{app_id}", "\"$2\"")
+                    .replace("{title}", "\"$3\"");
+                let id = window_id.to_string();
+                let args = [id.as_str(), app_id.unwrap_or_default(), title.unwrap_or_default()];
+                if let Err(e) = crate::system::spawn_shell_command(&command, &args) {
+                    tracing::warn!(%e, id = window_id, %command, "run command failed");
+                    return false;
                 }
             }
             WindowAction::Menu => {}
         }
+        true
+    }
+
+    /// Runs `close` right away, or when `confirm_close` is set, only once the
+    /// user picks it from a small menu at the pointer. The menu doesn't block
+    /// the main loop; dismissing it cancels.
+    fn confirm_close(state: &SharedState, prompt: &str, close: impl Fn() + 'static) {
+        if !state.settings().confirm_close() {
+            close();
+            return;
+        }
+
+        let menu = Menu::new();
+        menu.set_reserve_toggle_size(false);
+
+        let header = MenuItem::with_label(prompt);
+        header.set_sensitive(false);
+        menu.append(&header);
+
+        let confirm = MenuItem::with_label("Close");
+        confirm.connect_activate(move |_| close());
+        menu.append(&confirm);
+        menu.append(&MenuItem::with_label("Cancel"));
+
+        menu.show_all();
+        menu.popup_at_pointer(None);
     }
 
 	#[tracing::instrument(level = "TRACE", skip(self))]
 	fn display_context_menu(&self, window_id: u64) {
+		if self.state.settings().menu_focuses_first() && !self.state.settings().dry_run() {
+		    if let Err(e) = self.state.compositor().focus_window(window_id) {
+		        tracing::warn!(%e, id = window_id, "focus before menu failed");
+		    }
+		}
+
 		let menu = Menu::new();
 		menu.set_reserve_toggle_size(false);
 
@@ -344,9 +1302,11 @@ impl WindowButton {
 		    menu.append(&item);
 		    
 		    let state = self.state.clone();
-		    let action = menu_item.action.clone();
+		    let app_id = self.reported_app_id.clone();
+		    let title = self.title.clone();
+		    let actions = menu_item.action.clone();
 		    item.connect_activate(move |_| {
-		        Self::execute_action(&state, window_id, &action);
+		        Self::execute_actions(&state, window_id, app_id.as_deref(), title.borrow().as_deref(), actions.actions());
 		    });
 		}
 
@@ -357,13 +1317,36 @@ impl WindowButton {
 	fn clone_for_menu(&self) -> Self {
 		Self {
 		    app_id: self.app_id.clone(),
+		    reported_app_id: self.reported_app_id.clone(),
+		    root: self.root.clone(),
 		    gtk_button: self.gtk_button.clone(),
 		    layout_box: self.layout_box.clone(),
+		    overlay: self.overlay.clone(),
+		    count_badge: self.count_badge.clone(),
+		    index_label: self.index_label.clone(),
+		    rule_badge: self.rule_badge.clone(),
+		    close_button: self.close_button.clone(),
 		    title_label: self.title_label.clone(),
-		    display_titles: self.display_titles,
+		    separator_label: self.separator_label.clone(),
+		    display_titles: self.display_titles.clone(),
+		    app_label: self.app_label.clone(),
 		    state: self.state.clone(),
 		    window_id: self.window_id,
 		    title: self.title.clone(),
+		    urgent_timeout: self.urgent_timeout.clone(),
+		    output_class: self.output_class.clone(),
+		    workspace_class: self.workspace_class.clone(),
+		    workspace_name_class: self.workspace_name_class.clone(),
+		    compact: self.compact.clone(),
+		    pulse_timeout: self.pulse_timeout.clone(),
+		    compositor_urgent: self.compositor_urgent.clone(),
+		    long_press_timeout: self.long_press_timeout.clone(),
+		    long_press_fired: self.long_press_fired.clone(),
+		    title_applied_at: self.title_applied_at.clone(),
+		    pending_title: self.pending_title.clone(),
+		    title_timeout: self.title_timeout.clone(),
+		    tab_group: self.tab_group.clone(),
+		    width_tween: self.width_tween.clone(),
 		}
 	}
 
@@ -390,12 +1373,10 @@ impl WindowButton {
         self.gtk_button.connect_drag_begin(move |widget, _| {
             tracing::info!("drag initiated");
 
-            if let Some(parent) = widget.parent() {
-                if let Ok(container) = parent.downcast::<gtk::Box>() {
-                    let position = container.child_position(widget);
-                    *pos_for_begin.borrow_mut() = position;
-                    tracing::info!("stored initial position: {}", position);
-                }
+            if let Some((container, slot)) = taskbar_slot(widget.upcast_ref()) {
+                let position = container.child_position(&slot);
+                *pos_for_begin.borrow_mut() = position;
+                tracing::info!("stored initial position: {}", position);
             }
 
             widget.style_context().add_class("dragging");
@@ -415,15 +1396,15 @@ impl WindowButton {
         self.gtk_button.connect_drag_motion(move |widget, ctx, _x, _y, _time| {
             if let Some(source) = ctx.drag_get_source_widget() {
                 if source != *widget {
-                    if let Some(parent) = widget.parent() {
-                        if let Ok(container) = parent.downcast::<gtk::Box>() {
-                            let source_pos = container.child_position(&source);
-                            let target_pos = container.child_position(widget);
-
-                            if source_pos != target_pos {
-                                container.reorder_child(&source, target_pos);
-                                tracing::trace!("reordered from {} to {}", source_pos, target_pos);
-                            }
+                    if let (Some((container, source_slot)), Some((_, target_slot))) =
+                        (taskbar_slot(&source), taskbar_slot(widget.upcast_ref()))
+                    {
+                        let source_pos = container.child_position(&source_slot);
+                        let target_pos = container.child_position(&target_slot);
+
+                        if source_pos != target_pos {
+                            container.reorder_child(&source_slot, target_pos);
+                            tracing::trace!("reordered from {} to {}", source_pos, target_pos);
                         }
                     }
                 }
@@ -444,23 +1425,21 @@ impl WindowButton {
             if let Some(text) = data.text() {
                 if let Ok(dragged_window_id) = text.parse::<u64>() {
                     if let Some(source) = ctx.drag_get_source_widget() {
-                        if let Some(parent) = source.parent() {
-                            if let Ok(container) = parent.downcast::<gtk::Box>() {
-                                let start_pos = *pos_for_drop.borrow();
-                                let end_pos = container.child_position(&source);
-                                let delta = end_pos - start_pos;
-
-                                tracing::info!("position change: {} -> {} (delta: {})", start_pos, end_pos, delta);
-
-                                match state.compositor().reposition_window(dragged_window_id, delta) {
-                                    Ok(()) => {
-                                        tracing::info!("reposition successful");
-                                        ctx.drag_finish(true, false, time);
-                                        return;
-                                    }
-                                    Err(e) => {
-                                        tracing::error!("reposition failed: {}", e);
-                                    }
+                        if let Some((container, slot)) = taskbar_slot(&source) {
+                            let start_pos = *pos_for_drop.borrow();
+                            let end_pos = container.child_position(&slot);
+                            let delta = end_pos - start_pos;
+
+                            tracing::info!("position change: {} -> {} (delta: {})", start_pos, end_pos, delta);
+
+                            match state.compositor().reposition_window(dragged_window_id, delta) {
+                                Ok(()) => {
+                                    tracing::info!("reposition successful");
+                                    ctx.drag_finish(true, false, time);
+                                    return;
+                                }
+                                Err(e) => {
+                                    tracing::error!("reposition failed: {}", e);
                                 }
                             }
                         }
@@ -477,8 +1456,11 @@ impl WindowButton {
         let last_allocation = RefCell::new(None);
         let container = self.layout_box.clone();
         let label = self.title_label.clone();
-        let show_titles = self.display_titles;
+        let separator = self.separator_label.clone();
+        let icon_position = self.state.settings().icon_position();
         let icon_dimension = self.state.settings().icon_size();
+        let auto_icon_size = self.state.settings().auto_icon_size();
+        let fallback_icon = self.state.settings().fallback_icon().to_string();
 
         self.gtk_button.connect_size_allocate(move |button, allocation| {
             let mut needs_render = container.children().is_empty();
@@ -496,37 +1478,65 @@ impl WindowButton {
             }
 
             if needs_render {
-                let dimension = icon_dimension;
+                // With `auto_icon_size`, `icon_size` is only an upper bound and
+                // the icon shrinks to fit a thin bar.
+                let dimension = if auto_icon_size {
+                    let style_ctx = button.style_context();
+                    let padding = style_ctx.padding(button.state_flags());
+                    let border = style_ctx.border(button.state_flags());
+                    let insets = i32::from(padding.top() + padding.bottom() + border.top() + border.bottom());
+                    (allocation.height() - insets).min(icon_dimension).max(1)
+                } else {
+                    icon_dimension
+                };
 
                 let icon_image = Self::load_icon_image(icon_path.as_ref(), button, dimension)
+                    .or_else(|| {
+                        let fallback_path = PathBuf::from(&fallback_icon);
+                        if fallback_path.is_absolute() {
+                            Self::load_icon_image(Some(&fallback_path), button, dimension)
+                        } else {
+                            None
+                        }
+                    })
                     .unwrap_or_else(|| {
-                        static FALLBACK: &str = "application-x-executable";
-
                         ICON_THEME_INSTANCE.with(|theme| {
                             theme.lookup_icon_for_scale(
-                                FALLBACK,
+                                &fallback_icon,
                                 dimension,
                                 button.scale_factor(),
                                 IconLookupFlags::empty(),
                             )
                         })
                         .and_then(|info| Self::load_icon_image(info.filename().as_ref(), button, dimension))
-                        .unwrap_or_else(|| gtk::Image::from_icon_name(Some(FALLBACK), IconSize::Button))
+                        .unwrap_or_else(|| gtk::Image::from_icon_name(Some(&fallback_icon), IconSize::Button))
                     });
 
                 let container_copy = container.clone();
                 let label_copy = label.clone();
+                let separator_copy = separator.clone();
                 let button_copy = button.clone();
                 gtk::glib::source::idle_add_local_once(move || {
                     for child in container_copy.children() {
                         container_copy.remove(&child);
                     }
 
-                    container_copy.pack_start(&icon_image, false, false, 0);
+                    if icon_position == IconPosition::Right {
+                        container_copy.pack_end(&icon_image, false, false, 0);
+                    } else {
+                        container_copy.pack_start(&icon_image, false, false, 0);
+                    }
 
-                    if show_titles {
-                        container_copy.pack_start(&label_copy, true, true, 0);
+                    // The title is always packed; `refresh_title_visibility`
+                    // hides it for buttons that don't show one.
+                    if let Some(separator) = &separator_copy {
+                        if icon_position == IconPosition::Right {
+                            container_copy.pack_end(separator, false, false, 0);
+                        } else {
+                            container_copy.pack_start(separator, false, false, 0);
+                        }
                     }
+                    container_copy.pack_start(&label_copy, true, true, 0);
 
                     container_copy.show_all();
                     button_copy.show_all();
@@ -553,15 +1563,308 @@ impl WindowButton {
         .map(|surface| gtk::Image::from_surface(Some(&surface)))
     }
 	pub fn resize_for_width(&self, width: i32) {
-		if self.display_titles {
-		    let icon_dim = self.state.settings().icon_size();
-		    let icon_gap = self.state.settings().icon_spacing();
-		    let max_chars = ((width - icon_dim - icon_gap - 16) / 8).max(0);
-		    self.title_label.set_max_width_chars(max_chars);
-		    
-		    if max_chars == 0 {
-		        self.title_label.hide();
-		    }
-		}
+		self.title_label.set_max_width_chars(title_max_chars(self.state.settings(), width));
+		self.refresh_title_visibility();
 	}
-}
\ No newline at end of file
+
+    /// Hides the title in favour of an icon-only button, e.g. when the
+    /// taskbar is too crowded for titles to be readable.
+    pub fn set_compact(&self, compact: bool) {
+        if self.compact.replace(compact) != compact {
+            self.refresh_title_visibility();
+        }
+    }
+
+    fn refresh_title_visibility(&self) {
+        let visible = self.display_titles.get()
+            && !self.compact.get()
+            && !self.title_label.text().is_empty()
+            && self.title_label.max_width_chars() > 0;
+        self.title_label.set_visible(visible);
+        if let Some(separator) = &self.separator_label {
+            separator.set_visible(visible);
+        }
+    }
+}
+
+/// Minimizes the window and remembers where it came from, so `restore` can
+/// send it back there.
+fn minimize(state: &SharedState, window_id: u64) -> Result<(), ModuleError> {
+    match state.compositor().minimize_window(window_id)? {
+        Some(origin) => state.remember_minimized_origin(window_id, origin),
+        None => tracing::debug!(id = window_id, "minimized window had no origin workspace"),
+    }
+    Ok(())
+}
+
+/// Moves a minimized window back to the workspace `minimize` took it from.
+fn restore(state: &SharedState, window_id: u64) -> Result<(), ModuleError> {
+    state.compositor().restore_window(window_id, state.take_minimized_origin(window_id))
+}
+
+fn pointer_inside(widget: &impl gtk::prelude::IsA<gtk::Widget>, x: f64, y: f64) -> bool {
+    let allocation = widget.allocation();
+    x >= 0.0 && y >= 0.0 && x < allocation.width() as f64 && y < allocation.height() as f64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LeftClick {
+    Unfocused,
+    Focused,
+    Double,
+}
+
+/// Decides which action list a left click runs. The first click of a double
+/// click has already run `left_click_focused`; with no double-click action
+/// (or a zero window) the second one is just another single click.
+fn classify_left_click(focused: bool, since_last: Duration, double_click_window: Duration, has_double_click: bool) -> LeftClick {
+    if !focused {
+        LeftClick::Unfocused
+    } else if has_double_click && !double_click_window.is_zero() && since_last < double_click_window {
+        LeftClick::Double
+    } else {
+        LeftClick::Focused
+    }
+}
+
+/// Finds the taskbar box a window button sits in, along with the button's
+/// wrapper (the direct child of the box, which is what gets reordered).
+fn taskbar_slot(button: &gtk::Widget) -> Option<(gtk::Box, gtk::Widget)> {
+    let slot = button.parent()?;
+    let container = slot.parent()?.downcast::<gtk::Box>().ok()?;
+    Some((container, slot))
+}
+
+/// Roughly how many title characters fit in a button `width` pixels wide,
+/// leaving room for the icon (and `icon_title_separator`) when it sits beside
+/// the title. The guess assumes
+/// 8px glyphs; `title_max_chars` replaces it with a fixed cap.
+fn title_max_chars(settings: &Settings, width: i32) -> i32 {
+    if let Some(max_chars) = settings.title_max_chars() {
+        return max_chars.max(0);
+    }
+
+    let separator_room = settings.icon_title_separator()
+        .map_or(0, |separator| separator.chars().count() as i32 * 8 + settings.icon_spacing());
+    let icon_room = match settings.icon_position() {
+        IconPosition::Top => 0,
+        IconPosition::Left | IconPosition::Right => settings.icon_size() + settings.icon_spacing() + separator_room,
+    };
+    ((width - icon_room - 16) / 8).max(0)
+}
+
+/// Icon-only button for a pinned app that has no open window; clicking it
+/// launches the app.
+pub struct PinnedButton {
+    gtk_button: gtk::Button,
+}
+
+impl PinnedButton {
+    pub fn create(state: &SharedState, pinned: &crate::settings::PinnedApp) -> Self {
+        let gtk_button = gtk::Button::new();
+        gtk_button.set_relief(ReliefStyle::None);
+        gtk_button.style_context().add_class("pinned");
+        gtk_button.style_context().add_class("launcher");
+        gtk_button.set_tooltip_text(Some(&format!("Launch {}", state.settings().app_label(&pinned.app_id))));
+        BUTTON_STYLES.with(|provider| {
+            gtk_button.style_context().add_provider(provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+        });
+        apply_color_styles(state.settings(), &gtk_button);
+
+        // The icon surface needs the button's GDK window, so load it once realized.
+        let icon_path = state.icon_resolver().resolve(&pinned.app_id);
+        let icon_size = state.settings().icon_size();
+        let fallback_icon = state.settings().fallback_icon().to_string();
+        gtk_button.connect_realize(move |button| {
+            let image = WindowButton::load_icon_image(icon_path.as_ref(), button, icon_size)
+                .unwrap_or_else(|| gtk::Image::from_icon_name(Some(&fallback_icon), IconSize::Button));
+            button.set_image(Some(&image));
+            button.set_always_show_image(true);
+        });
+
+        let state = state.clone();
+        let pinned = pinned.clone();
+        gtk_button.connect_clicked(move |_| {
+            if state.settings().dry_run() {
+                tracing::info!(app_id = %pinned.app_id, "dry run: skipping launch");
+                return;
+            }
+            let result = match &pinned.command {
+                Some(command) => crate::system::spawn_shell_command(command, &[]).map_err(|e| e.to_string()),
+                None => crate::system::launch_desktop_entry(&pinned.desktop_id).map_err(|e| e.to_string()),
+            };
+            if let Err(e) = result {
+                tracing::warn!(%e, app_id = %pinned.app_id, "failed to launch pinned app");
+            }
+        });
+
+        Self { gtk_button }
+    }
+
+    pub fn get_widget(&self) -> &gtk::Button {
+        &self.gtk_button
+    }
+}
+
+/// The "+N" button shown after the last window button when `max_buttons` hides
+/// some windows. Clicking it lists the hidden windows for focusing.
+pub struct OverflowButton {
+    gtk_button: gtk::Button,
+    hidden: Rc<RefCell<Vec<(u64, Option<String>, String)>>>,
+}
+
+impl OverflowButton {
+    pub fn create(state: &SharedState) -> Self {
+        let gtk_button = gtk::Button::new();
+        gtk_button.set_relief(ReliefStyle::None);
+        gtk_button.style_context().add_class("overflow-button");
+        gtk_button.set_no_show_all(true);
+        BUTTON_STYLES.with(|provider| {
+            gtk_button.style_context().add_provider(provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+        });
+
+        let hidden: Rc<RefCell<Vec<(u64, Option<String>, String)>>> = Rc::new(RefCell::new(Vec::new()));
+
+        let state = state.clone();
+        let hidden_windows = hidden.clone();
+        gtk_button.connect_clicked(move |_| {
+            let menu = Menu::new();
+            menu.set_reserve_toggle_size(false);
+
+            for (window_id, app_id, label) in hidden_windows.borrow().iter() {
+                let item = MenuItem::with_label(label);
+                menu.append(&item);
+
+                let state = state.clone();
+                let (window_id, app_id, label) = (*window_id, app_id.clone(), label.clone());
+                item.connect_activate(move |_| {
+                    let action = crate::settings::WindowAction::FocusWindow;
+                    WindowButton::execute_action(&state, window_id, app_id.as_deref(), Some(&label), &action);
+                });
+            }
+
+            menu.show_all();
+            menu.popup_at_pointer(None);
+        });
+
+        Self { gtk_button, hidden }
+    }
+
+    pub fn get_widget(&self) -> &gtk::Button {
+        &self.gtk_button
+    }
+
+    /// Lists `windows` in the menu and shows the button, or hides it when
+    /// nothing overflows.
+    pub fn set_hidden(&self, settings: &Settings, windows: &[&WindowInfo]) {
+        *self.hidden.borrow_mut() = windows
+            .iter()
+            .map(|window| {
+                let label = window.title.clone()
+                    .or_else(|| window.app_id.as_deref().map(|id| settings.app_label(id).to_string()))
+                    .unwrap_or_else(|| format!("Window {}", window.id));
+                (window.id, window.reported_app_id().map(str::to_string), label)
+            })
+            .collect();
+
+        if windows.is_empty() {
+            self.gtk_button.hide();
+        } else {
+            self.gtk_button.set_label(&format!("+{}", windows.len()));
+            self.gtk_button.show();
+        }
+    }
+}
+
+/// Runs an `empty_area_actions` entry. Window actions are skipped because the
+/// click didn't land on a window. Returns whether the action went through.
+pub fn run_empty_area_action(state: &SharedState, action: &crate::settings::WindowAction) -> bool {
+    use crate::settings::WindowAction;
+    if state.settings().dry_run() && !matches!(action, WindowAction::None | WindowAction::Menu) {
+        tracing::info!(?action, "dry run: skipping empty area action");
+        return true;
+    }
+    match action {
+        WindowAction::None => {}
+        WindowAction::Menu => {
+            let menu = Menu::new();
+            menu.set_reserve_toggle_size(false);
+
+            for menu_item in &state.settings().empty_area_actions().menu {
+                let item = MenuItem::with_label(&menu_item.label);
+                menu.append(&item);
+
+                let state = state.clone();
+                let actions = menu_item.action.clone();
+                item.connect_activate(move |_| {
+                    for (step, action) in actions.actions().iter().enumerate() {
+                        if *action == WindowAction::Menu {
+                            continue;
+                        }
+                        if !run_empty_area_action(&state, action) {
+                            tracing::warn!(step = step + 1, ?action, "empty area action sequence stopped");
+                            return;
+                        }
+                    }
+                });
+            }
+
+            menu.show_all();
+            menu.popup_at_pointer(None);
+        }
+        WindowAction::RunCommand(command) => {
+            if let Err(e) = crate::system::spawn_shell_command(command, &[]) {
+                tracing::warn!(%e, %command, "run command failed");
+                return false;
+            }
+        }
+        other => {
+            tracing::warn!(action = ?other, "action needs a window and can't be used on the empty area");
+            return false;
+        }
+    }
+    true
+}
+
+/// Builds a `prefix-value` class name, replacing characters that aren't valid
+/// in a CSS identifier with `-`.
+fn css_class_name(prefix: &str, value: &str) -> String {
+    let sanitized: String = value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    format!("{prefix}-{sanitized}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::{classify_left_click, LeftClick};
+
+    const WINDOW: Duration = Duration::from_millis(300);
+
+    #[test]
+    fn unfocused_click_ignores_timing() {
+        assert_eq!(classify_left_click(false, Duration::ZERO, WINDOW, true), LeftClick::Unfocused);
+    }
+
+    #[test]
+    fn quick_second_click_is_double() {
+        assert_eq!(classify_left_click(true, Duration::from_millis(100), WINDOW, true), LeftClick::Double);
+    }
+
+    #[test]
+    fn slow_second_click_is_single() {
+        assert_eq!(classify_left_click(true, Duration::from_millis(300), WINDOW, true), LeftClick::Focused);
+    }
+
+    #[test]
+    fn no_double_click_action_means_single() {
+        assert_eq!(classify_left_click(true, Duration::from_millis(100), WINDOW, false), LeftClick::Focused);
+    }
+
+    #[test]
+    fn zero_window_disables_double_click() {
+        assert_eq!(classify_left_click(true, Duration::ZERO, Duration::ZERO, true), LeftClick::Focused);
+    }
+}